@@ -1,6 +1,9 @@
 mod utils;
 
+use std::rc::Rc;
+use std::cell::RefCell;
 use wand::{self, core, prelude::js::*};
+use wand::component::{EventKind, MouseButton, Modifiers};
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -25,7 +28,7 @@ pub fn start() {
 
 #[wasm_bindgen]
 pub struct Application {
-    app: wand::core::Application,
+    app: wand::core::ApplicationRef,
 }
 
 #[macro_export]
@@ -73,30 +76,116 @@ impl Application {
         scene.add_section(&section5);
         app.register(scene);
 
+        // Weak, as the state owns its listeners
+        let weak_state = Rc::downgrade(&state);
+        state.borrow().on("section5", EventKind::Click, move |ev| {
+            if let Some(state) = weak_state.upgrade() {
+                if let Some(cursor) = state.borrow().fetch_span("cursor") {
+                    cursor.borrow_mut().dispatch(Box::new(format!("Clicked: x: {}, y: {}", ev.pos.x(), ev.pos.y())));
+                }
+            }
+        });
+
         Self {
-            app,
+            app: Rc::new(RefCell::new(app)),
         }
     }
 
+    pub fn start(&self) {
+        core::Application::start(&self.app);
+    }
+
+    pub fn stop(&self) {
+        self.app.borrow().stop();
+    }
+
+    pub fn pause(&self) {
+        self.app.borrow().pause();
+    }
+
+    pub fn resume(&self) {
+        self.app.borrow_mut().resume();
+    }
+
     pub fn draw(&self) {
-        self.app.draw();
+        self.app.borrow().draw();
     }
 
     pub fn on_size_change(&mut self) {
-        self.app.on_resize();
+        self.app.borrow_mut().on_resize();
     }
 
     pub fn on_mouse_move(&mut self, x: f64, y: f64) {
-        self.app.on_mouse_move(x, y);
+        let mut app = self.app.borrow_mut();
+        app.on_mouse_move(x, y);
         {
-            let state = self.app.get_state();
-            let state = state.borrow_mut();
-            let cursor = state.fetch_span("cursor").unwrap();
+            let state = app.get_state();
+            let cursor = state.borrow().fetch_span("cursor").unwrap();
             let mut cursor = cursor.borrow_mut();
             // log!("Call {}", cursor.get_name());
             cursor.as_mut().dispatch(Box::new(format!("Cursor: x: {}, y: {}", x, y)));
         }
-        self.app.draw();
+    }
+
+    pub fn on_mouse_down(&mut self, x: f64, y: f64, button: i16, modifiers: u8) {
+        self.app.borrow_mut().on_mouse_down(x, y, Self::button(button), Modifiers::from_bits(modifiers));
+    }
+
+    pub fn on_mouse_up(&mut self, x: f64, y: f64, button: i16, modifiers: u8) {
+        self.app.borrow_mut().on_mouse_up(x, y, Self::button(button), Modifiers::from_bits(modifiers));
+    }
+
+    pub fn on_click(&mut self, x: f64, y: f64, button: i16, modifiers: u8) {
+        self.app.borrow_mut().on_click(x, y, Self::button(button), Modifiers::from_bits(modifiers));
+    }
+
+    pub fn on_double_click(&mut self, x: f64, y: f64, button: i16, modifiers: u8) {
+        self.app.borrow_mut().on_double_click(x, y, Self::button(button), Modifiers::from_bits(modifiers));
+    }
+
+    pub fn on_context_menu(&mut self, x: f64, y: f64, modifiers: u8) {
+        self.app.borrow_mut().on_context_menu(x, y, Modifiers::from_bits(modifiers));
+    }
+
+    pub fn on_wheel(&mut self, x: f64, y: f64, delta_x: f64, delta_y: f64, modifiers: u8) {
+        self.app.borrow_mut().on_wheel(x, y, delta_x, delta_y, Modifiers::from_bits(modifiers));
+    }
+
+    pub fn on_pointer_enter(&mut self, x: f64, y: f64) {
+        self.app.borrow_mut().on_pointer_enter(x, y);
+    }
+
+    pub fn on_pointer_leave(&mut self, x: f64, y: f64) {
+        self.app.borrow_mut().on_pointer_leave(x, y);
+    }
+
+    pub fn on_touch_start(&mut self, id: i32, x: f64, y: f64) {
+        self.app.borrow_mut().on_touch_start(id, x, y);
+    }
+
+    pub fn on_touch_move(&mut self, id: i32, x: f64, y: f64) {
+        self.app.borrow_mut().on_touch_move(id, x, y);
+    }
+
+    pub fn on_touch_end(&mut self, id: i32, x: f64, y: f64) {
+        self.app.borrow_mut().on_touch_end(id, x, y);
+    }
+
+    pub fn on_touch_cancel(&mut self, id: i32) {
+        self.app.borrow_mut().on_touch_cancel(id);
+    }
+
+    /// Returns true when the page should prevent the default handling of the key
+    pub fn on_keydown(&mut self, key: &str, modifiers: u8) -> bool {
+        self.app.borrow().on_keydown_with(key, Modifiers::from_bits(modifiers)).default_prevented
+    }
+
+    pub fn on_keyup(&mut self, key: &str, modifiers: u8) -> bool {
+        self.app.borrow().on_keyup_with(key, Modifiers::from_bits(modifiers)).default_prevented
+    }
+
+    fn button(button: i16) -> MouseButton {
+        MouseButton::from_index(button).unwrap_or(MouseButton::Primary)
     }
 
 }
@@ -5,13 +5,13 @@ window.$mobile = /mobile/i.test(window.navigator.userAgent) || typeof window.ori
 const app = wand_app.Application.new();
 const canvas = document.getElementById("canvas");
 const resize = () => {
-    canvas.width = document.documentElement.clientWidth;
-    canvas.height = document.documentElement.clientHeight;
+    canvas.style.width = document.documentElement.clientWidth + "px";
+    canvas.style.height = document.documentElement.clientHeight + "px";
     app.on_size_change();
-    app.draw();
 }
 
 resize();
+app.start();
 
 window.addEventListener("resize", resize);
 window.addEventListener("mousemove", e => {
@@ -19,3 +19,46 @@ window.addEventListener("mousemove", e => {
   app.on_mouse_move(e.clientX - rec.left, e.clientY - rec.top);
 });
 
+// Shift, ctrl, alt and meta as the bits of `Modifiers::from_bits`
+const modifiers = e => (e.shiftKey ? 1 : 0) | (e.ctrlKey ? 2 : 0) | (e.altKey ? 4 : 0) | (e.metaKey ? 8 : 0);
+const pointer = (name, handler) => canvas.addEventListener(name, e => {
+  let rec = canvas.getBoundingClientRect();
+  handler(e, e.clientX - rec.left, e.clientY - rec.top);
+}, { passive: false });
+
+pointer("mousedown", (e, x, y) => app.on_mouse_down(x, y, e.button, modifiers(e)));
+pointer("mouseup", (e, x, y) => app.on_mouse_up(x, y, e.button, modifiers(e)));
+pointer("click", (e, x, y) => app.on_click(x, y, e.button, modifiers(e)));
+pointer("dblclick", (e, x, y) => app.on_double_click(x, y, e.button, modifiers(e)));
+pointer("mouseenter", (e, x, y) => app.on_pointer_enter(x, y));
+pointer("mouseleave", (e, x, y) => app.on_pointer_leave(x, y));
+pointer("contextmenu", (e, x, y) => {
+  e.preventDefault();
+  app.on_context_menu(x, y, modifiers(e));
+});
+pointer("wheel", (e, x, y) => {
+  e.preventDefault();
+  app.on_wheel(x, y, e.deltaX, e.deltaY, modifiers(e));
+});
+
+// Every changed touch is tracked on its own, by its identifier
+const touch = (name, handler) => canvas.addEventListener(name, e => {
+  e.preventDefault();
+  let rec = canvas.getBoundingClientRect();
+  for (const t of e.changedTouches) {
+    handler(t.identifier, t.clientX - rec.left, t.clientY - rec.top);
+  }
+}, { passive: false });
+
+touch("touchstart", (id, x, y) => app.on_touch_start(id, x, y));
+touch("touchmove", (id, x, y) => app.on_touch_move(id, x, y));
+touch("touchend", (id, x, y) => app.on_touch_end(id, x, y));
+touch("touchcancel", id => app.on_touch_cancel(id));
+
+window.addEventListener("keydown", e => {
+  // Tab moves the focus between spans instead of leaving the canvas
+  if (app.on_keydown(e.key, modifiers(e)) || e.key === "Tab") e.preventDefault();
+});
+window.addEventListener("keyup", e => {
+  if (app.on_keyup(e.key, modifiers(e))) e.preventDefault();
+});
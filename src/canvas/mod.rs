@@ -0,0 +1,70 @@
+use std::any::Any;
+use std::rc::Rc;
+
+mod web;
+mod null;
+mod recorder;
+mod raster;
+
+pub use self::null::NullCanvas;
+pub use self::recorder::{RecordingCanvas, DrawCommand, Frame};
+pub use self::raster::{RasterCanvas, parse_color};
+
+/// Drawing backend the whole UI tree renders through.
+///
+/// The method set mirrors the subset of the canvas 2d API wand relies on,
+/// so the web context is a thin wrapper while other backends (recording,
+/// software raster) can be plugged in for native rendering and testing.
+pub trait Canvas2D {
+    fn save(&self);
+    fn restore(&self);
+    fn translate(&self, x: f64, y: f64);
+    fn scale(&self, x: f64, y: f64);
+    fn set_global_alpha(&self, alpha: f64);
+
+    fn begin_path(&self);
+    fn close_path(&self);
+    fn move_to(&self, x: f64, y: f64);
+    fn line_to(&self, x: f64, y: f64);
+    fn rect(&self, x: f64, y: f64, w: f64, h: f64);
+    fn arc(&self, x: f64, y: f64, radius: f64, start: f64, end: f64);
+    fn ellipse(&self, x: f64, y: f64, radius_x: f64, radius_y: f64, rotation: f64, start: f64, end: f64);
+    fn stroke(&self);
+    fn fill(&self);
+    /// Restrict drawing to the current path until the state is restored
+    fn clip(&self);
+
+    fn stroke_rect(&self, x: f64, y: f64, w: f64, h: f64);
+    fn fill_rect(&self, x: f64, y: f64, w: f64, h: f64);
+    fn clear_rect(&self, x: f64, y: f64, w: f64, h: f64);
+
+    fn set_stroke_style(&self, style: &str);
+    fn set_fill_style(&self, style: &str);
+    fn set_line_width(&self, width: f64);
+
+    fn set_font(&self, font: &str);
+    fn set_text_align(&self, align: &str);
+    fn set_text_baseline(&self, baseline: &str);
+    fn fill_text(&self, text: &str, x: f64, y: f64);
+    /// Width of the text with the current font
+    fn measure_text(&self, text: &str) -> f64;
+
+    /// Create an offscreen surface of the same backend, `None` if unsupported
+    fn create_layer(&self, w: u32, h: u32) -> Option<Rc<dyn Canvas2D>>;
+    /// Draw a surface created by `create_layer` stretched over the given rect
+    fn draw_layer(&self, layer: &dyn Canvas2D, x: f64, y: f64, w: f64, h: f64);
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Pixel size of a css font string like `"14px Arial"`, 10 if none is found
+pub fn font_size(font: &str) -> f64 {
+    font.split_whitespace()
+        .filter_map(|part| part.trim_end_matches("px").parse::<f64>().ok())
+        .next()
+        .unwrap_or(10.)
+}
+
+/// Deterministic text width estimate used by the native backends
+pub fn approximate_width(text: &str, font_size: f64) -> f64 {
+    text.chars().count() as f64 * font_size * 0.5
+}
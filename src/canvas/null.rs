@@ -0,0 +1,69 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::rc::Rc;
+use crate::canvas::{Canvas2D, font_size, approximate_width};
+
+/// Backend discarding every draw call, for headless applications.
+///
+/// Text is measured with a fixed advance of half the font size per character,
+/// so layout depending on `measure_text` stays deterministic.
+pub struct NullCanvas {
+    font_size: Cell<f64>,
+}
+
+impl NullCanvas {
+    pub fn new() -> Self {
+        Self {
+            font_size: Cell::new(10.),
+        }
+    }
+}
+
+impl Canvas2D for NullCanvas {
+    fn save(&self) {}
+    fn restore(&self) {}
+    fn translate(&self, _x: f64, _y: f64) {}
+    fn scale(&self, _x: f64, _y: f64) {}
+    fn set_global_alpha(&self, _alpha: f64) {}
+
+    fn begin_path(&self) {}
+    fn close_path(&self) {}
+    fn move_to(&self, _x: f64, _y: f64) {}
+    fn line_to(&self, _x: f64, _y: f64) {}
+    fn rect(&self, _x: f64, _y: f64, _w: f64, _h: f64) {}
+    fn arc(&self, _x: f64, _y: f64, _radius: f64, _start: f64, _end: f64) {}
+    fn ellipse(&self, _x: f64, _y: f64, _radius_x: f64, _radius_y: f64, _rotation: f64, _start: f64, _end: f64) {}
+    fn stroke(&self) {}
+    fn fill(&self) {}
+    fn clip(&self) {}
+
+    fn stroke_rect(&self, _x: f64, _y: f64, _w: f64, _h: f64) {}
+    fn fill_rect(&self, _x: f64, _y: f64, _w: f64, _h: f64) {}
+    fn clear_rect(&self, _x: f64, _y: f64, _w: f64, _h: f64) {}
+
+    fn set_stroke_style(&self, _style: &str) {}
+    fn set_fill_style(&self, _style: &str) {}
+    fn set_line_width(&self, _width: f64) {}
+
+    fn set_font(&self, font: &str) {
+        self.font_size.set(font_size(font));
+    }
+
+    fn set_text_align(&self, _align: &str) {}
+    fn set_text_baseline(&self, _baseline: &str) {}
+    fn fill_text(&self, _text: &str, _x: f64, _y: f64) {}
+
+    fn measure_text(&self, text: &str) -> f64 {
+        approximate_width(text, self.font_size.get())
+    }
+
+    fn create_layer(&self, _w: u32, _h: u32) -> Option<Rc<dyn Canvas2D>> {
+        Some(Rc::new(NullCanvas::new()))
+    }
+
+    fn draw_layer(&self, _layer: &dyn Canvas2D, _x: f64, _y: f64, _w: f64, _h: f64) {}
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
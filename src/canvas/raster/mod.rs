@@ -0,0 +1,617 @@
+mod font;
+mod png;
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::f64::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::canvas::{Canvas2D, font_size, approximate_width};
+
+type Color = [u8; 4];
+type Point = (f64, f64);
+
+#[derive(Clone)]
+struct DrawState {
+    fill: Color,
+    stroke: Color,
+    line_width: f64,
+    font_size: f64,
+    text_align: String,
+    text_baseline: String,
+    alpha: f64,
+    // Axis aligned transform as (scale x, scale y, translate x, translate y)
+    transform: (f64, f64, f64, f64),
+    // Clip box in pixels as (left, top, right, bottom)
+    clip: Option<(f64, f64, f64, f64)>,
+}
+
+impl DrawState {
+    fn new() -> Self {
+        Self {
+            fill: [0, 0, 0, 255],
+            stroke: [0, 0, 0, 255],
+            line_width: 1.,
+            font_size: 10.,
+            text_align: "start".to_string(),
+            text_baseline: "alphabetic".to_string(),
+            alpha: 1.,
+            transform: (1., 1., 0., 0.),
+            clip: None,
+        }
+    }
+
+    fn apply(&self, x: f64, y: f64) -> Point {
+        let (sx, sy, tx, ty) = self.transform;
+        (x * sx + tx, y * sy + ty)
+    }
+
+    fn apply_rect(&self, x: f64, y: f64, w: f64, h: f64) -> (f64, f64, f64, f64) {
+        let (x, y) = self.apply(x, y);
+        (x, y, w * self.transform.0, h * self.transform.1)
+    }
+
+    fn paint(&self, color: Color) -> Color {
+        [color[0], color[1], color[2], (color[3] as f64 * self.alpha).round() as u8]
+    }
+}
+
+/// Software rasterizer rendering into an RGBA8 buffer.
+///
+/// Shapes are drawn without anti-aliasing, sampling pixel centers, and text uses
+/// a built-in 5x7 bitmap font whose advance matches `NullCanvas` measuring, so
+/// frames are identical across machines.
+pub struct RasterCanvas {
+    width: u32,
+    height: u32,
+    pixels: RefCell<Vec<u8>>,
+    state: RefCell<DrawState>,
+    stack: RefCell<Vec<DrawState>>,
+    path: RefCell<Vec<Vec<Point>>>,
+}
+
+impl RasterCanvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: RefCell::new(vec![0; width as usize * height as usize * 4]),
+            state: RefCell::new(DrawState::new()),
+            stack: RefCell::new(Vec::new()),
+            path: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Copy of the RGBA8 buffer, row by row from the top left
+    pub fn pixels(&self) -> Vec<u8> {
+        self.pixels.borrow().clone()
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let pixels = self.pixels.borrow();
+        [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+    }
+
+    /// Fill the whole buffer with a css color, e.g. a background before rendering
+    pub fn fill_background(&self, style: &str) {
+        if let Some(color) = parse_color(style) {
+            for pixel in self.pixels.borrow_mut().chunks_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode(self.width, self.height, &self.pixels.borrow())
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_png())
+    }
+
+    fn blend(&self, pixels: &mut [u8], x: i64, y: i64, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || color[3] == 0 {
+            return;
+        }
+        if !self.in_clip(x, y) {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = color[3] as u32;
+        if alpha == 255 {
+            pixels[i..i + 4].copy_from_slice(&color);
+            return;
+        }
+        let dst_alpha = pixels[i + 3] as u32;
+        let out_alpha = alpha + dst_alpha * (255 - alpha) / 255;
+        for c in 0..3 {
+            let src = color[c] as u32 * alpha;
+            let dst = pixels[i + c] as u32 * dst_alpha * (255 - alpha) / 255;
+            pixels[i + c] = if out_alpha == 0 { 0 } else { ((src + dst) / out_alpha) as u8 };
+        }
+        pixels[i + 3] = out_alpha as u8;
+    }
+
+    fn in_clip(&self, x: i64, y: i64) -> bool {
+        match self.state.borrow().clip {
+            Some((left, top, right, bottom)) => {
+                let (cx, cy) = (x as f64 + 0.5, y as f64 + 0.5);
+                cx >= left && cx < right && cy >= top && cy < bottom
+            },
+            None => true,
+        }
+    }
+
+    /// Pixel index range whose centers fall within [min, max)
+    fn span(&self, min: f64, max: f64, limit: u32) -> (i64, i64) {
+        let start = (min - 0.5).ceil().max(0.) as i64;
+        let end = ((max - 0.5).ceil() as i64).min(limit as i64);
+        (start, end)
+    }
+
+    fn fill_area(&self, x: f64, y: f64, w: f64, h: f64, color: Color) {
+        let (x0, x1) = self.span(x.min(x + w), x.max(x + w), self.width);
+        let (y0, y1) = self.span(y.min(y + h), y.max(y + h), self.height);
+        let mut pixels = self.pixels.borrow_mut();
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.blend(&mut pixels, px, py, color);
+            }
+        }
+    }
+
+    fn stroke_segment(&self, a: Point, b: Point, width: f64, color: Color) {
+        let half = width.max(1.) / 2.;
+        // Bounding box is padded, the distance test below decides coverage
+        let (x0, x1) = self.span(a.0.min(b.0) - half - 1., a.0.max(b.0) + half + 1., self.width);
+        let (y0, y1) = self.span(a.1.min(b.1) - half - 1., a.1.max(b.1) + half + 1., self.height);
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length = dx * dx + dy * dy;
+        let mut pixels = self.pixels.borrow_mut();
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let (cx, cy) = (px as f64 + 0.5, py as f64 + 0.5);
+                let t = if length == 0. { 0. } else { (((cx - a.0) * dx + (cy - a.1) * dy) / length).max(0.).min(1.) };
+                let (ex, ey) = (cx - (a.0 + t * dx), cy - (a.1 + t * dy));
+                if ex * ex + ey * ey <= half * half {
+                    self.blend(&mut pixels, px, py, color);
+                }
+            }
+        }
+    }
+
+    fn stroke_polyline(&self, points: &[Point], width: f64, color: Color) {
+        // A lone point strokes nothing, like a canvas
+        for pair in points.windows(2) {
+            self.stroke_segment(pair[0], pair[1], width, color);
+        }
+    }
+
+    /// Scanline fill with the nonzero winding rule, subpaths closed implicitly
+    fn fill_polygons(&self, polygons: &[Vec<Point>], color: Color) {
+        let mut edges = Vec::new();
+        for polygon in polygons.iter().filter(|p| p.len() > 2) {
+            for i in 0..polygon.len() {
+                let a = polygon[i];
+                let b = polygon[(i + 1) % polygon.len()];
+                if a.1 != b.1 {
+                    edges.push((a, b));
+                }
+            }
+        }
+        if edges.is_empty() {
+            return;
+        }
+
+        let min_y = edges.iter().map(|(a, b)| a.1.min(b.1)).fold(std::f64::INFINITY, f64::min);
+        let max_y = edges.iter().map(|(a, b)| a.1.max(b.1)).fold(std::f64::NEG_INFINITY, f64::max);
+        let (y0, y1) = self.span(min_y, max_y, self.height);
+        let mut pixels = self.pixels.borrow_mut();
+        let mut crossings: Vec<(f64, i32)> = Vec::new();
+        for py in y0..y1 {
+            let cy = py as f64 + 0.5;
+            crossings.clear();
+            for (a, b) in edges.iter() {
+                let (top, bottom, winding) = if a.1 < b.1 { (a, b, 1) } else { (b, a, -1) };
+                if cy >= top.1 && cy < bottom.1 {
+                    let x = top.0 + (cy - top.1) / (bottom.1 - top.1) * (bottom.0 - top.0);
+                    crossings.push((x, winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let mut winding = 0;
+            for i in 0..crossings.len() {
+                winding += crossings[i].1;
+                if winding != 0 && i + 1 < crossings.len() {
+                    let (x0, x1) = self.span(crossings[i].0, crossings[i + 1].0, self.width);
+                    for px in x0..x1 {
+                        self.blend(&mut pixels, px, py, color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_point(&self, point: Point, connect: bool) {
+        let mut path = self.path.borrow_mut();
+        match path.last_mut() {
+            Some(ref mut subpath) if connect && !subpath.is_empty() => subpath.push(point),
+            _ => path.push(vec![point]),
+        }
+    }
+
+    fn push_curve(&self, x: f64, y: f64, radius_x: f64, radius_y: f64, rotation: f64, start: f64, end: f64) {
+        let state = self.state.borrow().clone();
+        let mut sweep = end - start;
+        if sweep >= PI * 2. {
+            sweep = PI * 2.;
+        } else {
+            while sweep < 0. {
+                sweep += PI * 2.;
+            }
+        }
+        let steps = ((radius_x.max(radius_y) * sweep / 2.).ceil() as usize).max(8).min(720);
+        let (sin_r, cos_r) = rotation.sin_cos();
+        for i in 0..=steps {
+            let angle = start + sweep * i as f64 / steps as f64;
+            let (px, py) = (radius_x * angle.cos(), radius_y * angle.sin());
+            self.push_point(state.apply(x + px * cos_r - py * sin_r, y + px * sin_r + py * cos_r), true);
+        }
+    }
+}
+
+impl Canvas2D for RasterCanvas {
+    fn save(&self) {
+        let state = self.state.borrow().clone();
+        self.stack.borrow_mut().push(state);
+    }
+
+    fn restore(&self) {
+        if let Some(state) = self.stack.borrow_mut().pop() {
+            *self.state.borrow_mut() = state;
+        }
+    }
+
+    fn translate(&self, x: f64, y: f64) {
+        let mut state = self.state.borrow_mut();
+        let (sx, sy, tx, ty) = state.transform;
+        state.transform = (sx, sy, tx + x * sx, ty + y * sy);
+    }
+
+    fn scale(&self, x: f64, y: f64) {
+        let mut state = self.state.borrow_mut();
+        let (sx, sy, tx, ty) = state.transform;
+        state.transform = (sx * x, sy * y, tx, ty);
+    }
+
+    fn set_global_alpha(&self, alpha: f64) {
+        if alpha >= 0. && alpha <= 1. {
+            self.state.borrow_mut().alpha = alpha;
+        }
+    }
+
+    fn begin_path(&self) {
+        self.path.borrow_mut().clear();
+    }
+
+    fn close_path(&self) {
+        let mut path = self.path.borrow_mut();
+        let first = path.last().and_then(|subpath| subpath.first().cloned());
+        if let Some(first) = first {
+            path.last_mut().unwrap().push(first);
+            path.push(vec![first]);
+        }
+    }
+
+    fn move_to(&self, x: f64, y: f64) {
+        let point = self.state.borrow().apply(x, y);
+        self.push_point(point, false);
+    }
+
+    fn line_to(&self, x: f64, y: f64) {
+        let point = self.state.borrow().apply(x, y);
+        self.push_point(point, true);
+    }
+
+    fn rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        let (x, y, w, h) = self.state.borrow().apply_rect(x, y, w, h);
+        let mut path = self.path.borrow_mut();
+        path.push(vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h), (x, y)]);
+        path.push(vec![(x, y)]);
+    }
+
+    fn arc(&self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        self.push_curve(x, y, radius, radius, 0., start, end);
+    }
+
+    fn ellipse(&self, x: f64, y: f64, radius_x: f64, radius_y: f64, rotation: f64, start: f64, end: f64) {
+        self.push_curve(x, y, radius_x, radius_y, rotation, start, end);
+    }
+
+    fn stroke(&self) {
+        let (color, width) = {
+            let state = self.state.borrow();
+            (state.paint(state.stroke), state.line_width * state.transform.0)
+        };
+        for subpath in self.path.borrow().iter() {
+            self.stroke_polyline(subpath, width, color);
+        }
+    }
+
+    fn fill(&self) {
+        let color = {
+            let state = self.state.borrow();
+            state.paint(state.fill)
+        };
+        self.fill_polygons(&self.path.borrow(), color);
+    }
+
+    fn clip(&self) {
+        // Clipping uses the bounding box of the path, exact for rectangles
+        let points: Vec<Point> = self.path.borrow().iter().flat_map(|subpath| subpath.iter().cloned()).collect();
+        if points.is_empty() {
+            return;
+        }
+        let left = points.iter().map(|p| p.0).fold(std::f64::INFINITY, f64::min);
+        let top = points.iter().map(|p| p.1).fold(std::f64::INFINITY, f64::min);
+        let right = points.iter().map(|p| p.0).fold(std::f64::NEG_INFINITY, f64::max);
+        let bottom = points.iter().map(|p| p.1).fold(std::f64::NEG_INFINITY, f64::max);
+        let mut state = self.state.borrow_mut();
+        state.clip = Some(match state.clip {
+            Some((l, t, r, b)) => (left.max(l), top.max(t), right.min(r), bottom.min(b)),
+            None => (left, top, right, bottom),
+        });
+    }
+
+    fn stroke_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        let (color, width, (x, y, w, h)) = {
+            let state = self.state.borrow();
+            (state.paint(state.stroke), state.line_width * state.transform.0, state.apply_rect(x, y, w, h))
+        };
+        self.stroke_polyline(&[(x, y), (x + w, y), (x + w, y + h), (x, y + h), (x, y)], width, color);
+    }
+
+    fn fill_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        let (color, (x, y, w, h)) = {
+            let state = self.state.borrow();
+            (state.paint(state.fill), state.apply_rect(x, y, w, h))
+        };
+        self.fill_area(x, y, w, h, color);
+    }
+
+    fn clear_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        let (x, y, w, h) = self.state.borrow().apply_rect(x, y, w, h);
+        let (x0, x1) = self.span(x.min(x + w), x.max(x + w), self.width);
+        let (y0, y1) = self.span(y.min(y + h), y.max(y + h), self.height);
+        let mut pixels = self.pixels.borrow_mut();
+        for py in y0..y1 {
+            let row = py as usize * self.width as usize;
+            for px in x0..x1 {
+                if self.in_clip(px, py) {
+                    let i = (row + px as usize) * 4;
+                    pixels[i..i + 4].copy_from_slice(&[0, 0, 0, 0]);
+                }
+            }
+        }
+    }
+
+    fn set_stroke_style(&self, style: &str) {
+        if let Some(color) = parse_color(style) {
+            self.state.borrow_mut().stroke = color;
+        }
+    }
+
+    fn set_fill_style(&self, style: &str) {
+        if let Some(color) = parse_color(style) {
+            self.state.borrow_mut().fill = color;
+        }
+    }
+
+    fn set_line_width(&self, width: f64) {
+        if width > 0. {
+            self.state.borrow_mut().line_width = width;
+        }
+    }
+
+    fn set_font(&self, font: &str) {
+        self.state.borrow_mut().font_size = font_size(font);
+    }
+
+    fn set_text_align(&self, align: &str) {
+        self.state.borrow_mut().text_align = align.to_string();
+    }
+
+    fn set_text_baseline(&self, baseline: &str) {
+        self.state.borrow_mut().text_baseline = baseline.to_string();
+    }
+
+    fn fill_text(&self, text: &str, x: f64, y: f64) {
+        let state = self.state.borrow().clone();
+        let color = state.paint(state.fill);
+        let (x, y) = state.apply(x, y);
+        let size = state.font_size * state.transform.0;
+        let width = approximate_width(text, size);
+        let scale = size * 0.5 / font::CELL_WIDTH as f64;
+        let height = font::GLYPH_HEIGHT as f64 * scale;
+        let left = match state.text_align.as_str() {
+            "center" => x - width / 2.,
+            "right" | "end" => x - width,
+            _ => x,
+        };
+        let top = match state.text_baseline.as_str() {
+            "top" | "hanging" => y,
+            "middle" => y - height / 2.,
+            _ => y - height,
+        };
+        for (index, c) in text.chars().enumerate() {
+            let origin = left + (index * font::CELL_WIDTH) as f64 * scale;
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for col in 0..font::GLYPH_WIDTH {
+                    if bits & (0x10 >> col) != 0 {
+                        self.fill_area(origin + col as f64 * scale, top + row as f64 * scale, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn measure_text(&self, text: &str) -> f64 {
+        approximate_width(text, self.state.borrow().font_size)
+    }
+
+    fn create_layer(&self, w: u32, h: u32) -> Option<Rc<dyn Canvas2D>> {
+        Some(Rc::new(RasterCanvas::new(w, h)))
+    }
+
+    fn draw_layer(&self, layer: &dyn Canvas2D, x: f64, y: f64, w: f64, h: f64) {
+        let layer = match layer.as_any().downcast_ref::<RasterCanvas>() {
+            Some(layer) => layer,
+            None => return,
+        };
+        let (state, source) = (self.state.borrow().clone(), layer.pixels.borrow());
+        let (x, y, w, h) = state.apply_rect(x, y, w, h);
+        if w <= 0. || h <= 0. {
+            return;
+        }
+        let (x0, x1) = self.span(x, x + w, self.width);
+        let (y0, y1) = self.span(y, y + h, self.height);
+        let mut pixels = self.pixels.borrow_mut();
+        // Nearest neighbour sampling of the layer
+        for py in y0..y1 {
+            let sy = (((py as f64 + 0.5 - y) / h * layer.height as f64) as usize).min(layer.height as usize - 1);
+            for px in x0..x1 {
+                let sx = (((px as f64 + 0.5 - x) / w * layer.width as f64) as usize).min(layer.width as usize - 1);
+                let i = (sy * layer.width as usize + sx) * 4;
+                let color = [source[i], source[i + 1], source[i + 2], source[i + 3]];
+                self.blend(&mut pixels, px, py, state.paint(color));
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Parse the css colors used across wand: hex, rgb(a) and common names
+pub fn parse_color(style: &str) -> Option<Color> {
+    let style = style.trim().to_ascii_lowercase();
+    if style.starts_with('#') {
+        let hex = &style[1..];
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        // All ascii from here, so byte offsets are char offsets
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|v| v * 17);
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return match hex.len() {
+            3 => Some([digit(0)?, digit(1)?, digit(2)?, 255]),
+            4 => Some([digit(0)?, digit(1)?, digit(2)?, digit(3)?]),
+            6 => Some([byte(0)?, byte(2)?, byte(4)?, 255]),
+            8 => Some([byte(0)?, byte(2)?, byte(4)?, byte(6)?]),
+            _ => None,
+        };
+    }
+    if style.starts_with("rgb") {
+        let open = style.find('(')?;
+        let close = style.rfind(')')?;
+        if close < open {
+            return None;
+        }
+        let values: Vec<f64> = style[open + 1..close]
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .ok()?;
+        let channel = |v: f64| v.max(0.).min(255.).round() as u8;
+        return match values.len() {
+            3 => Some([channel(values[0]), channel(values[1]), channel(values[2]), 255]),
+            4 => Some([channel(values[0]), channel(values[1]), channel(values[2]), channel(values[3] * 255.)]),
+            _ => None,
+        };
+    }
+    let color = match style.as_str() {
+        "transparent" => [0, 0, 0, 0],
+        "black" => [0, 0, 0, 255],
+        "white" => [255, 255, 255, 255],
+        "red" => [255, 0, 0, 255],
+        "green" => [0, 128, 0, 255],
+        "lime" => [0, 255, 0, 255],
+        "blue" => [0, 0, 255, 255],
+        "yellow" => [255, 255, 0, 255],
+        "cyan" | "aqua" => [0, 255, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255, 255],
+        "orange" => [255, 165, 0, 255],
+        "purple" => [128, 0, 128, 255],
+        "grey" | "gray" => [128, 128, 128, 255],
+        "lightgrey" | "lightgray" => [211, 211, 211, 255],
+        "darkgrey" | "darkgray" => [169, 169, 169, 255],
+        "darkgreen" => [0, 100, 0, 255],
+        _ => return None,
+    };
+    Some(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#07ce88"), Some([0x07, 0xce, 0x88, 255]));
+        assert_eq!(parse_color("#fff"), Some([255, 255, 255, 255]));
+        assert_eq!(parse_color("rgba(10, 20, 30, 0.5)"), Some([10, 20, 30, 128]));
+        assert_eq!(parse_color("White"), Some([255, 255, 255, 255]));
+        assert_eq!(parse_color("nonsense"), None);
+        assert_eq!(parse_color("#é1"), None);
+        assert_eq!(parse_color("#ffé"), None);
+        assert_eq!(parse_color("rgb)("), None);
+        assert_eq!(parse_color("rgb()"), None);
+    }
+
+    #[test]
+    fn test_rasterize_shapes() {
+        let canvas = RasterCanvas::new(20, 20);
+        canvas.set_fill_style("red");
+        canvas.fill_rect(2., 2., 4., 4.);
+        assert_eq!(canvas.pixel(3, 3), [255, 0, 0, 255]);
+        assert_eq!(canvas.pixel(6, 6), [0, 0, 0, 0]);
+
+        canvas.set_fill_style("blue");
+        canvas.begin_path();
+        canvas.move_to(10., 10.);
+        canvas.line_to(20., 10.);
+        canvas.line_to(10., 20.);
+        canvas.fill();
+        assert_eq!(canvas.pixel(11, 11), [0, 0, 255, 255]);
+        assert_eq!(canvas.pixel(19, 19), [0, 0, 0, 0]);
+
+        canvas.set_stroke_style("white");
+        canvas.begin_path();
+        canvas.arc(5., 15., 3., 0., PI * 2.);
+        canvas.stroke();
+        assert_eq!(canvas.pixel(7, 14), [255, 255, 255, 255]);
+        assert_eq!(canvas.pixel(5, 15), [0, 0, 0, 0]);
+
+        canvas.begin_path();
+        canvas.move_to(15.5, 3.5);
+        canvas.stroke();
+        assert_eq!(canvas.pixel(15, 3), [0, 0, 0, 0]);
+
+        let png = canvas.to_png();
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+}
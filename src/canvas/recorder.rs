@@ -0,0 +1,345 @@
+use std::fmt;
+use std::any::Any;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::cell::{Cell, RefCell};
+use crate::canvas::{Canvas2D, font_size, approximate_width};
+use crate::utils::unquote;
+
+/// A single draw call captured by `RecordingCanvas`
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Save,
+    Restore,
+    Translate { x: f64, y: f64 },
+    Scale { x: f64, y: f64 },
+    GlobalAlpha(f64),
+    BeginPath,
+    ClosePath,
+    MoveTo { x: f64, y: f64 },
+    LineTo { x: f64, y: f64 },
+    Rect { x: f64, y: f64, w: f64, h: f64 },
+    Arc { x: f64, y: f64, radius: f64, start: f64, end: f64 },
+    Ellipse { x: f64, y: f64, radius_x: f64, radius_y: f64, rotation: f64, start: f64, end: f64 },
+    Stroke,
+    Fill,
+    Clip,
+    StrokeRect { x: f64, y: f64, w: f64, h: f64 },
+    FillRect { x: f64, y: f64, w: f64, h: f64 },
+    ClearRect { x: f64, y: f64, w: f64, h: f64 },
+    StrokeStyle(String),
+    FillStyle(String),
+    LineWidth(f64),
+    Font(String),
+    TextAlign(String),
+    TextBaseline(String),
+    FillText { text: String, x: f64, y: f64 },
+    DrawLayer { x: f64, y: f64, w: f64, h: f64 },
+}
+
+impl fmt::Display for DrawCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawCommand::Save => write!(f, "save"),
+            DrawCommand::Restore => write!(f, "restore"),
+            DrawCommand::Translate { x, y } => write!(f, "translate {} {}", x, y),
+            DrawCommand::Scale { x, y } => write!(f, "scale {} {}", x, y),
+            DrawCommand::GlobalAlpha(alpha) => write!(f, "global_alpha {}", alpha),
+            DrawCommand::BeginPath => write!(f, "begin_path"),
+            DrawCommand::ClosePath => write!(f, "close_path"),
+            DrawCommand::MoveTo { x, y } => write!(f, "move_to {} {}", x, y),
+            DrawCommand::LineTo { x, y } => write!(f, "line_to {} {}", x, y),
+            DrawCommand::Rect { x, y, w, h } => write!(f, "rect {} {} {} {}", x, y, w, h),
+            DrawCommand::Arc { x, y, radius, start, end } =>
+                write!(f, "arc {} {} {} {} {}", x, y, radius, start, end),
+            DrawCommand::Ellipse { x, y, radius_x, radius_y, rotation, start, end } =>
+                write!(f, "ellipse {} {} {} {} {} {} {}", x, y, radius_x, radius_y, rotation, start, end),
+            DrawCommand::Stroke => write!(f, "stroke"),
+            DrawCommand::Fill => write!(f, "fill"),
+            DrawCommand::Clip => write!(f, "clip"),
+            DrawCommand::StrokeRect { x, y, w, h } => write!(f, "stroke_rect {} {} {} {}", x, y, w, h),
+            DrawCommand::FillRect { x, y, w, h } => write!(f, "fill_rect {} {} {} {}", x, y, w, h),
+            DrawCommand::ClearRect { x, y, w, h } => write!(f, "clear_rect {} {} {} {}", x, y, w, h),
+            DrawCommand::StrokeStyle(style) => write!(f, "stroke_style {:?}", style),
+            DrawCommand::FillStyle(style) => write!(f, "fill_style {:?}", style),
+            DrawCommand::LineWidth(width) => write!(f, "line_width {}", width),
+            DrawCommand::Font(font) => write!(f, "font {:?}", font),
+            DrawCommand::TextAlign(align) => write!(f, "text_align {:?}", align),
+            DrawCommand::TextBaseline(baseline) => write!(f, "text_baseline {:?}", baseline),
+            DrawCommand::FillText { text, x, y } => write!(f, "fill_text {} {} {:?}", x, y, text),
+            DrawCommand::DrawLayer { x, y, w, h } => write!(f, "draw_layer {} {} {} {}", x, y, w, h),
+        }
+    }
+}
+
+impl FromStr for DrawCommand {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (op, args) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, ""),
+        };
+
+        macro_rules! numbers {
+            ($n: expr) => {{
+                let values = args.split_whitespace().take($n)
+                    .map(|v| v.parse::<f64>().map_err(|_| format!("Invalid number {:?} in {:?}", v, line)))
+                    .collect::<Result<Vec<f64>, String>>()?;
+                if values.len() != $n {
+                    return Err(format!("Expected {} numbers in {:?}", $n, line));
+                }
+                values
+            }};
+        }
+
+        let command = match op {
+            "save" => DrawCommand::Save,
+            "restore" => DrawCommand::Restore,
+            "begin_path" => DrawCommand::BeginPath,
+            "close_path" => DrawCommand::ClosePath,
+            "stroke" => DrawCommand::Stroke,
+            "fill" => DrawCommand::Fill,
+            "clip" => DrawCommand::Clip,
+            "translate" => { let v = numbers!(2); DrawCommand::Translate { x: v[0], y: v[1] } },
+            "scale" => { let v = numbers!(2); DrawCommand::Scale { x: v[0], y: v[1] } },
+            "global_alpha" => { let v = numbers!(1); DrawCommand::GlobalAlpha(v[0]) },
+            "move_to" => { let v = numbers!(2); DrawCommand::MoveTo { x: v[0], y: v[1] } },
+            "line_to" => { let v = numbers!(2); DrawCommand::LineTo { x: v[0], y: v[1] } },
+            "rect" => { let v = numbers!(4); DrawCommand::Rect { x: v[0], y: v[1], w: v[2], h: v[3] } },
+            "arc" => {
+                let v = numbers!(5);
+                DrawCommand::Arc { x: v[0], y: v[1], radius: v[2], start: v[3], end: v[4] }
+            },
+            "ellipse" => {
+                let v = numbers!(7);
+                DrawCommand::Ellipse {
+                    x: v[0], y: v[1], radius_x: v[2], radius_y: v[3], rotation: v[4], start: v[5], end: v[6]
+                }
+            },
+            "stroke_rect" => { let v = numbers!(4); DrawCommand::StrokeRect { x: v[0], y: v[1], w: v[2], h: v[3] } },
+            "fill_rect" => { let v = numbers!(4); DrawCommand::FillRect { x: v[0], y: v[1], w: v[2], h: v[3] } },
+            "clear_rect" => { let v = numbers!(4); DrawCommand::ClearRect { x: v[0], y: v[1], w: v[2], h: v[3] } },
+            "draw_layer" => { let v = numbers!(4); DrawCommand::DrawLayer { x: v[0], y: v[1], w: v[2], h: v[3] } },
+            "line_width" => { let v = numbers!(1); DrawCommand::LineWidth(v[0]) },
+            "stroke_style" => DrawCommand::StrokeStyle(unquote(args)?),
+            "fill_style" => DrawCommand::FillStyle(unquote(args)?),
+            "font" => DrawCommand::Font(unquote(args)?),
+            "text_align" => DrawCommand::TextAlign(unquote(args)?),
+            "text_baseline" => DrawCommand::TextBaseline(unquote(args)?),
+            "fill_text" => {
+                let mut parts = args.splitn(3, ' ');
+                let x = parts.next().and_then(|v| v.parse::<f64>().ok());
+                let y = parts.next().and_then(|v| v.parse::<f64>().ok());
+                match (x, y, parts.next()) {
+                    (Some(x), Some(y), Some(text)) => DrawCommand::FillText { text: unquote(text)?, x, y },
+                    _ => return Err(format!("Invalid fill_text {:?}", line)),
+                }
+            },
+            _ => return Err(format!("Unknown draw command {:?}", line)),
+        };
+        Ok(command)
+    }
+}
+
+/// The draw calls of one rendered frame, serialized as one command per line
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Frame {
+    pub commands: Vec<DrawCommand>,
+}
+
+impl Frame {
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let commands = data.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.parse::<DrawCommand>())
+            .collect::<Result<Vec<DrawCommand>, String>>()?;
+        Ok(Self { commands })
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for command in self.commands.iter() {
+            writeln!(f, "{}", command)?;
+        }
+        Ok(())
+    }
+}
+
+/// Backend capturing every draw call for later inspection.
+///
+/// Text is measured like `NullCanvas` does, so recorded frames are stable across machines.
+/// Layers are recorders of their own, drawing one only records a `DrawLayer` command.
+pub struct RecordingCanvas {
+    commands: RefCell<Vec<DrawCommand>>,
+    font_size: Cell<f64>,
+}
+
+impl RecordingCanvas {
+    pub fn new() -> Self {
+        Self {
+            commands: RefCell::new(Vec::new()),
+            font_size: Cell::new(10.),
+        }
+    }
+
+    /// Take the commands recorded so far as a frame, leaving the recorder empty
+    pub fn take_frame(&self) -> Frame {
+        Frame { commands: self.commands.replace(Vec::new()) }
+    }
+
+    pub fn commands(&self) -> Vec<DrawCommand> {
+        self.commands.borrow().clone()
+    }
+
+    pub fn clear(&self) {
+        self.commands.borrow_mut().clear();
+    }
+
+    fn record(&self, command: DrawCommand) {
+        self.commands.borrow_mut().push(command);
+    }
+}
+
+impl Canvas2D for RecordingCanvas {
+    fn save(&self) {
+        self.record(DrawCommand::Save);
+    }
+
+    fn restore(&self) {
+        self.record(DrawCommand::Restore);
+    }
+
+    fn translate(&self, x: f64, y: f64) {
+        self.record(DrawCommand::Translate { x, y });
+    }
+
+    fn scale(&self, x: f64, y: f64) {
+        self.record(DrawCommand::Scale { x, y });
+    }
+
+    fn set_global_alpha(&self, alpha: f64) {
+        self.record(DrawCommand::GlobalAlpha(alpha));
+    }
+
+    fn begin_path(&self) {
+        self.record(DrawCommand::BeginPath);
+    }
+
+    fn close_path(&self) {
+        self.record(DrawCommand::ClosePath);
+    }
+
+    fn move_to(&self, x: f64, y: f64) {
+        self.record(DrawCommand::MoveTo { x, y });
+    }
+
+    fn line_to(&self, x: f64, y: f64) {
+        self.record(DrawCommand::LineTo { x, y });
+    }
+
+    fn rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        self.record(DrawCommand::Rect { x, y, w, h });
+    }
+
+    fn arc(&self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        self.record(DrawCommand::Arc { x, y, radius, start, end });
+    }
+
+    fn ellipse(&self, x: f64, y: f64, radius_x: f64, radius_y: f64, rotation: f64, start: f64, end: f64) {
+        self.record(DrawCommand::Ellipse { x, y, radius_x, radius_y, rotation, start, end });
+    }
+
+    fn stroke(&self) {
+        self.record(DrawCommand::Stroke);
+    }
+
+    fn fill(&self) {
+        self.record(DrawCommand::Fill);
+    }
+
+    fn clip(&self) {
+        self.record(DrawCommand::Clip);
+    }
+
+    fn stroke_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        self.record(DrawCommand::StrokeRect { x, y, w, h });
+    }
+
+    fn fill_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        self.record(DrawCommand::FillRect { x, y, w, h });
+    }
+
+    fn clear_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        self.record(DrawCommand::ClearRect { x, y, w, h });
+    }
+
+    fn set_stroke_style(&self, style: &str) {
+        self.record(DrawCommand::StrokeStyle(style.to_string()));
+    }
+
+    fn set_fill_style(&self, style: &str) {
+        self.record(DrawCommand::FillStyle(style.to_string()));
+    }
+
+    fn set_line_width(&self, width: f64) {
+        self.record(DrawCommand::LineWidth(width));
+    }
+
+    fn set_font(&self, font: &str) {
+        self.font_size.set(font_size(font));
+        self.record(DrawCommand::Font(font.to_string()));
+    }
+
+    fn set_text_align(&self, align: &str) {
+        self.record(DrawCommand::TextAlign(align.to_string()));
+    }
+
+    fn set_text_baseline(&self, baseline: &str) {
+        self.record(DrawCommand::TextBaseline(baseline.to_string()));
+    }
+
+    fn fill_text(&self, text: &str, x: f64, y: f64) {
+        self.record(DrawCommand::FillText { text: text.to_string(), x, y });
+    }
+
+    fn measure_text(&self, text: &str) -> f64 {
+        approximate_width(text, self.font_size.get())
+    }
+
+    fn create_layer(&self, _w: u32, _h: u32) -> Option<Rc<dyn Canvas2D>> {
+        Some(Rc::new(RecordingCanvas::new()))
+    }
+
+    fn draw_layer(&self, _layer: &dyn Canvas2D, x: f64, y: f64, w: f64, h: f64) {
+        self.record(DrawCommand::DrawLayer { x, y, w, h });
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let canvas = RecordingCanvas::new();
+        canvas.set_font("12px Arial");
+        canvas.set_stroke_style("#07ce88");
+        canvas.stroke_rect(1.5, 2., 30., 40.25);
+        canvas.arc(10., 10., 5., 0., 6.283185307179586);
+        canvas.fill_text("Say \"hi\"\n", 4., 8.);
+        let frame = canvas.take_frame();
+        assert_eq!(frame.commands.len(), 5);
+        assert!(canvas.commands().is_empty());
+
+        let data = frame.to_string();
+        assert_eq!(data.lines().nth(2), Some("stroke_rect 1.5 2 30 40.25"));
+        assert_eq!(Frame::parse(&data), Ok(frame));
+    }
+}
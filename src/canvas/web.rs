@@ -0,0 +1,136 @@
+use std::any::Any;
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use crate::canvas::Canvas2D;
+use crate::prelude::renderer::{self, Context2D, HtmlCanvasElement};
+
+impl Canvas2D for Context2D {
+    fn save(&self) {
+        Context2D::save(self);
+    }
+
+    fn restore(&self) {
+        Context2D::restore(self);
+    }
+
+    fn translate(&self, x: f64, y: f64) {
+        let _ = Context2D::translate(self, x, y);
+    }
+
+    fn scale(&self, x: f64, y: f64) {
+        let _ = Context2D::scale(self, x, y);
+    }
+
+    fn set_global_alpha(&self, alpha: f64) {
+        Context2D::set_global_alpha(self, alpha);
+    }
+
+    fn begin_path(&self) {
+        Context2D::begin_path(self);
+    }
+
+    fn close_path(&self) {
+        Context2D::close_path(self);
+    }
+
+    fn move_to(&self, x: f64, y: f64) {
+        Context2D::move_to(self, x, y);
+    }
+
+    fn line_to(&self, x: f64, y: f64) {
+        Context2D::line_to(self, x, y);
+    }
+
+    fn rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        Context2D::rect(self, x, y, w, h);
+    }
+
+    fn arc(&self, x: f64, y: f64, radius: f64, start: f64, end: f64) {
+        let _ = Context2D::arc(self, x, y, radius, start, end);
+    }
+
+    fn ellipse(&self, x: f64, y: f64, radius_x: f64, radius_y: f64, rotation: f64, start: f64, end: f64) {
+        let _ = Context2D::ellipse(self, x, y, radius_x, radius_y, rotation, start, end);
+    }
+
+    fn stroke(&self) {
+        Context2D::stroke(self);
+    }
+
+    fn fill(&self) {
+        Context2D::fill(self);
+    }
+
+    fn clip(&self) {
+        Context2D::clip(self);
+    }
+
+    fn stroke_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        Context2D::stroke_rect(self, x, y, w, h);
+    }
+
+    fn fill_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        Context2D::fill_rect(self, x, y, w, h);
+    }
+
+    fn clear_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        Context2D::clear_rect(self, x, y, w, h);
+    }
+
+    fn set_stroke_style(&self, style: &str) {
+        Context2D::set_stroke_style(self, &JsValue::from_str(style));
+    }
+
+    fn set_fill_style(&self, style: &str) {
+        Context2D::set_fill_style(self, &JsValue::from_str(style));
+    }
+
+    fn set_line_width(&self, width: f64) {
+        Context2D::set_line_width(self, width);
+    }
+
+    fn set_font(&self, font: &str) {
+        Context2D::set_font(self, font);
+    }
+
+    fn set_text_align(&self, align: &str) {
+        Context2D::set_text_align(self, align);
+    }
+
+    fn set_text_baseline(&self, baseline: &str) {
+        Context2D::set_text_baseline(self, baseline);
+    }
+
+    fn fill_text(&self, text: &str, x: f64, y: f64) {
+        let _ = Context2D::fill_text(self, text, x, y);
+    }
+
+    fn measure_text(&self, text: &str) -> f64 {
+        match Context2D::measure_text(self, text) {
+            Ok(metrics) => metrics.width(),
+            Err(_) => 0.,
+        }
+    }
+
+    fn create_layer(&self, w: u32, h: u32) -> Option<Rc<dyn Canvas2D>> {
+        let document = renderer::window()?.document()?;
+        let canvas = document.create_element("canvas").ok()?
+            .dyn_into::<HtmlCanvasElement>().ok()?;
+        canvas.set_width(w);
+        canvas.set_height(h);
+        let context = canvas.get_context("2d").ok()??
+            .dyn_into::<Context2D>().ok()?;
+        Some(Rc::new(context))
+    }
+
+    fn draw_layer(&self, layer: &dyn Canvas2D, x: f64, y: f64, w: f64, h: f64) {
+        let canvas = layer.as_any().downcast_ref::<Context2D>().and_then(|layer| layer.canvas());
+        if let Some(canvas) = canvas {
+            let _ = self.draw_image_with_html_canvas_element_and_dw_and_dh(&canvas, x, y, w, h);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
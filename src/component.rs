@@ -1,4 +1,48 @@
+use crate::drag::DragPayload;
 
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, w: f64, h: f64) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn right(&self) -> f64 {
+        self.x + self.w
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y + self.h
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w <= 0. || self.h <= 0.
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right() && other.x < self.right() && self.y < other.bottom() && other.y < self.bottom()
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(x, y, self.right().max(other.right()) - x, self.bottom().max(other.bottom()) - y)
+    }
+
+    /// Grow by `d` on every side
+    pub fn inflate(&self, d: f64) -> Rect {
+        Rect::new(self.x - d, self.y - d, self.w + 2. * d, self.h + 2. * d)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub struct Position(f64, f64);
 
 impl Position {
@@ -6,6 +50,14 @@ impl Position {
         Self(x, y)
     }
 
+    pub fn x(&self) -> f64 {
+        self.0
+    }
+
+    pub fn y(&self) -> f64 {
+        self.1
+    }
+
     pub fn in_rec(&self, x: f64, y: f64, w: f64, h: f64) -> bool {
         self.0 > x && self.0 < x + w && self.1 > y && self.1 < y + h
     }
@@ -15,15 +67,285 @@ impl Position {
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[repr(u8)]
 pub enum EventType {
     MouseMove,
+    MouseDown,
+    MouseUp,
+    Click,
+    DoubleClick,
+    Wheel,
+    ContextMenu,
+    /// The pointer entered the node, delivered to it alone without propagating
+    PointerEnter,
+    /// The pointer left the node, delivered to it alone without propagating
+    PointerLeave,
+    /// Delivered to the focused span, then bubbling up through its sections
+    KeyDown,
+    KeyUp,
+    /// The span received the focus, delivered to it alone
+    Focus,
+    /// The span lost the focus, delivered to it alone
+    Blur,
+    /// The pointer pressed on a drag source moved past the threshold, delivered to the
+    /// source alone. Preventing its default cancels the drag.
+    DragStart,
+    /// A drag entered a drop target accepting its payload, delivered to the target alone
+    DragEnter,
+    /// The pointer moved while over the drop target, delivered to the target alone
+    DragOver,
+    /// A drag left the drop target, or was cancelled over it
+    DragLeave,
+    /// The pointer was released over the drop target, delivered to the target alone
+    Drop,
+    /// The drag finished, dropped or not, delivered to the source alone
+    DragEnd,
+    /// The user scrolled the section, carrying how far its items moved in `delta_x` and
+    /// `delta_y`, delivered to it alone
+    Scroll,
+    /// A touch came in contact, carrying its pointer id
+    TouchStart,
+    TouchMove,
+    TouchEnd,
+    /// The platform interrupted the touch
+    TouchCancel,
+    /// Gestures recognized from the touches, see `gesture::Gesture`
+    Tap,
+    LongPress,
+    /// Carries the movement since the previous pan in `delta_x` and `delta_y`
+    Pan,
+    /// Carries the change of distance between the touches since the previous pinch in `scale`
+    Pinch,
+    /// Carries the turn since the previous rotation in `rotation`
+    Rotate,
+    /// Carries the overall movement in `delta_x` and `delta_y`
+    Swipe,
+}
+
+/// Kind of event listened to through `StateProto::on`
+pub type EventKind = EventType;
+
+/// Mouse buttons, numbered like the DOM `MouseEvent.button`
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[repr(u8)]
+pub enum MouseButton {
+    Primary,
+    Auxiliary,
+    Secondary,
+    Back,
+    Forward,
+}
+
+impl MouseButton {
+    pub fn from_index(index: i16) -> Option<Self> {
+        match index {
+            0 => Some(MouseButton::Primary),
+            1 => Some(MouseButton::Auxiliary),
+            2 => Some(MouseButton::Secondary),
+            3 => Some(MouseButton::Back),
+            4 => Some(MouseButton::Forward),
+            _ => None,
+        }
+    }
+
+    pub fn index(self) -> i16 {
+        self as i16
+    }
+}
+
+/// Modifier keys held while an event happened
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+impl Modifiers {
+    pub const SHIFT: u8 = 1;
+    pub const CTRL: u8 = 2;
+    pub const ALT: u8 = 4;
+    pub const META: u8 = 8;
+
+    /// Modifiers from a mask of `SHIFT`, `CTRL`, `ALT` and `META`, handy across wasm-bindgen
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            shift: bits & Self::SHIFT != 0,
+            ctrl: bits & Self::CTRL != 0,
+            alt: bits & Self::ALT != 0,
+            meta: bits & Self::META != 0,
+        }
+    }
+
+    pub fn bits(&self) -> u8 {
+        let mut bits = 0;
+        if self.shift { bits |= Self::SHIFT; }
+        if self.ctrl { bits |= Self::CTRL; }
+        if self.alt { bits |= Self::ALT; }
+        if self.meta { bits |= Self::META; }
+        bits
+    }
 }
 
+/// Stage of the propagation an event is delivered in, like DOM events
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum EventPhase {
+    /// Not being dispatched
+    None,
+    /// Going down from the scene through the ancestors of the target
+    Capture,
+    /// Delivered to the deepest node under the pointer
+    Target,
+    /// Going back up through the ancestors of the target
+    Bubble,
+}
+
+#[derive(Clone, Debug)]
 pub struct Event {
     pub pos: Position,
     pub ev: EventType,
+    /// Button that changed state, for button and click events
+    pub button: Option<MouseButton>,
+    pub modifiers: Modifiers,
+    /// Scroll amount of wheel events in pixels
+    pub delta_x: f64,
+    pub delta_y: f64,
+    /// Key of keyboard events, as the DOM `KeyboardEvent.key`
+    pub key: Option<String>,
+    /// Data carried by drag and drop events
+    pub payload: Option<DragPayload>,
+    /// Pointer id of touch events
+    pub pointer_id: Option<i32>,
+    /// Relative scale of pinch events, 1 otherwise
+    pub scale: f64,
+    /// Turn of rotate events in radians, clockwise
+    pub rotation: f64,
+    pub phase: EventPhase,
+    /// Set by `stop_propagation`, nothing receives the event afterwards
     pub consumed: bool,
+    /// Set by `prevent_default`, the framework and the embedding page skip their default handling
+    pub default_prevented: bool,
+}
+
+impl Event {
+    pub fn new(ev: EventType, x: f64, y: f64) -> Self {
+        Self {
+            pos: Position::new(x, y),
+            ev,
+            button: None,
+            modifiers: Modifiers::default(),
+            delta_x: 0.,
+            delta_y: 0.,
+            key: None,
+            payload: None,
+            pointer_id: None,
+            scale: 1.,
+            rotation: 0.,
+            phase: EventPhase::None,
+            consumed: false,
+            default_prevented: false,
+        }
+    }
+
+    /// Stop delivering the event to siblings and ancestors
+    pub fn stop_propagation(&mut self) {
+        self.consumed = true;
+    }
+
+    /// Skip the default handling of the event while still propagating it
+    pub fn prevent_default(&mut self) {
+        self.default_prevented = true;
+    }
+
+    /// Keyboard event, without any position
+    pub fn new_key(ev: EventType, key: &str) -> Self {
+        let mut event = Self::new(ev, 0., 0.);
+        event.key = Some(key.to_string());
+        event
+    }
+
+    pub fn with_button(mut self, button: MouseButton) -> Self {
+        self.button = Some(button);
+        self
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    pub fn with_delta(mut self, delta_x: f64, delta_y: f64) -> Self {
+        self.delta_x = delta_x;
+        self.delta_y = delta_y;
+        self
+    }
+
+    pub fn with_payload(mut self, payload: DragPayload) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    pub fn with_pointer_id(mut self, pointer_id: i32) -> Self {
+        self.pointer_id = Some(pointer_id);
+        self
+    }
+}
+
+
+/// Timing of the current tick, durations in milliseconds
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct FrameTime {
+    /// Time since the previous tick
+    pub dt: f64,
+    /// Time accumulated over all ticks
+    pub elapsed: f64,
+    /// Index of the tick, starting from 0
+    pub frame: u64,
+    /// Whether `dt` is a fixed step of the application rather than the frame time
+    pub fixed: bool,
+}
+
+impl FrameTime {
+    /// Time since the previous tick in seconds
+    pub fn dt_secs(&self) -> f64 {
+        self.dt / 1000.
+    }
 }
 
+/// Splits elapsed time into whole steps of a fixed length, carrying the remainder over
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FixedStep {
+    step: f64,
+    accumulator: f64,
+}
+
+impl FixedStep {
+    /// Most steps taken at once, a longer gap is dropped rather than caught up with
+    pub const MAX_STEPS: u32 = 8;
+
+    pub fn new(step: f64) -> Self {
+        Self { step, accumulator: 0. }
+    }
+
+    pub fn get_step(&self) -> f64 {
+        self.step
+    }
+
+    /// Add `dt` milliseconds, returns the number of steps due
+    pub fn advance(&mut self, dt: f64) -> u32 {
+        self.accumulator += dt;
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < Self::MAX_STEPS {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if self.accumulator >= self.step {
+            // Too far behind, drop the backlog rather than spiral
+            self.accumulator = 0.;
+        }
+        steps
+    }
+}
@@ -2,7 +2,10 @@ use crate::traits::*;
 use crate::content::Content;
 use crate::component::*;
 use crate::prelude::renderer::RendererContext;
+use crate::layer;
+use crate::scroll::ScrollPhysics;
 
+/// Direction the items flow in, the container scrolls that way when they overflow it
 #[derive(PartialEq, Eq)]
 #[repr(u8)]
 pub enum Scrollable {
@@ -11,6 +14,8 @@ pub enum Scrollable {
     None = 2,
 }
 
+const SCROLLBAR_WIDTH: f64 = 2.;
+
 pub struct Container {
     scroll: Scrollable,
 
@@ -23,7 +28,21 @@ pub struct Container {
     pub bottom: f64,
 
     inventory: Vec<Content>,
-    
+
+    // Scroll offset of the items, applied when drawing and hit testing them so their layout
+    // stays put, and the extent they cover from the origin of the container
+    scroll_x: f64,
+    scroll_y: f64,
+    content_w: f64,
+    content_h: f64,
+
+    // Momentum and overscroll of pointer drags when kinetic, with the velocity measured
+    // from the distance dragged since the previous tick
+    physics: Option<ScrollPhysics>,
+    dragging: bool,
+    velocity: (f64, f64),
+    drag_delta: (f64, f64),
+
     padding_x: f32,
     padding_y: f32,
     padding_min_x: f32,
@@ -56,6 +75,16 @@ impl Container {
             
             inventory: Vec::new(),
 
+            scroll_x: 0.,
+            scroll_y: 0.,
+            content_w: 0.,
+            content_h: 0.,
+
+            physics: None,
+            dragging: false,
+            velocity: (0., 0.),
+            drag_delta: (0., 0.),
+
             padding_x,
             padding_y,
             padding_min_x,
@@ -73,29 +102,210 @@ impl Container {
         self.top = y + padding_y;
         self.right = x + w - padding_x;
         self.bottom = y + h - padding_y;
-        self.x = self.left;
-        self.y = self.top;
 
         self.resize();
     }
 
 
+    // Lay the items out from the origin, measuring the extent they cover
     fn resize(&mut self) {
+        self.x = self.left;
+        self.y = self.top;
+        let (origin_x, origin_y) = (self.x, self.y);
+        let (mut extent_x, mut extent_y) = (origin_x, origin_y);
         for i in 0..self.inventory.len(){
+            // Items of scrolling containers are sized against the visible area
+            let (right, bottom) = match self.scroll {
+                Scrollable::X => (self.x + self.right - self.left, self.bottom),
+                Scrollable::Y => (self.right, self.y + self.bottom - self.top),
+                Scrollable::None => (self.right, self.bottom),
+            };
             let item = self.inventory.get_mut(i).unwrap();
             let w;
             let h;
             loop {
-                let (width, height, fit) = (*item).on_resize(self.x, self.y, self.right, self.bottom);
-                log!("resize content item: {} {} {} {}, {} {} {}", self.x, self.y, self.right, self.bottom, width, height, fit);
+                let (width, height, fit) = (*item).on_resize(self.x, self.y, right, bottom);
+                log!("resize content item: {} {} {} {}, {} {} {}", self.x, self.y, right, bottom, width, height, fit);
                 if fit {
                     w = width;
                     h = height;
                     break;
                 }
             }
+            // Spans only take room in scrolling containers, others lay them over each other
+            let (w, h) = if self.scroll == Scrollable::None && item.is_span() { (0., 0.) } else { (w, h) };
+            extent_x = extent_x.max(self.x + w);
+            extent_y = extent_y.max(self.y + h);
             self.update_cursor(w, h);
         }
+        self.content_w = extent_x - origin_x;
+        self.content_h = extent_y - origin_y;
+
+        // Keep the offset within the items after they shrank, kinetic ones bounce back instead
+        let (max_x, max_y) = self.max_scroll();
+        if self.physics.is_none() {
+            self.scroll_x = self.scroll_x.min(max_x);
+            self.scroll_y = self.scroll_y.min(max_y);
+        }
+    }
+
+    pub fn is_scrollable(&self) -> bool {
+        self.scroll != Scrollable::None
+    }
+
+    /// Scroll offset of the items
+    pub fn get_scroll(&self) -> (f64, f64) {
+        (self.scroll_x, self.scroll_y)
+    }
+
+    /// Extent of the items as laid out
+    pub fn get_content_size(&self) -> (f64, f64) {
+        (self.content_w, self.content_h)
+    }
+
+    /// Largest offset keeping the container filled, zero along the axes it does not scroll
+    pub fn max_scroll(&self) -> (f64, f64) {
+        let max_x = (self.content_w - (self.right - self.left)).max(0.);
+        let max_y = (self.content_h - (self.bottom - self.top)).max(0.);
+        match self.scroll {
+            Scrollable::X => (max_x, 0.),
+            Scrollable::Y => (0., max_y),
+            Scrollable::None => (0., 0.),
+        }
+    }
+
+    /// Scroll to the offset, clamped to the items, returns how far the items moved.
+    /// Stops any momentum.
+    pub fn scroll_to(&mut self, x: f64, y: f64) -> (f64, f64) {
+        let (max_x, max_y) = self.max_scroll();
+        self.velocity = (0., 0.);
+        self.set_offset(x.max(0.).min(max_x), y.max(0.).min(max_y))
+    }
+
+    // Move the items to the offset as is, returns how far they moved
+    fn set_offset(&mut self, x: f64, y: f64) -> (f64, f64) {
+        let moved = (x - self.scroll_x, y - self.scroll_y);
+        self.scroll_x = x;
+        self.scroll_y = y;
+        moved
+    }
+
+    pub fn scroll_by(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        self.scroll_to(self.scroll_x + dx, self.scroll_y + dy)
+    }
+
+    /// Give the container momentum and overscroll when dragged, `None` to scroll it directly
+    pub fn set_kinetic(&mut self, physics: Option<ScrollPhysics>) {
+        self.physics = physics;
+        self.velocity = (0., 0.);
+    }
+
+    pub fn is_kinetic(&self) -> bool {
+        self.physics.is_some()
+    }
+
+    /// Velocity of the items in pixels per millisecond
+    pub fn get_velocity(&self) -> (f64, f64) {
+        self.velocity
+    }
+
+    /// The pointer pressed the container, stopping its momentum until released
+    pub fn grab(&mut self) {
+        if self.physics.is_some() {
+            self.dragging = true;
+            self.velocity = (0., 0.);
+            self.drag_delta = (0., 0.);
+        }
+    }
+
+    /// Scroll along with the pointer dragging the items, pulling them past the edges
+    /// with resistance when kinetic. Returns how far the items moved.
+    pub fn drag_by(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let physics = match self.physics {
+            Some(physics) => physics,
+            None => return self.scroll_by(dx, dy),
+        };
+        let (max_x, max_y) = self.max_scroll();
+        // Only the axis the container scrolls along moves
+        let x = if self.scroll == Scrollable::X { physics.drag(self.scroll_x, max_x, dx) } else { self.scroll_x };
+        let y = if self.scroll == Scrollable::Y { physics.drag(self.scroll_y, max_y, dy) } else { self.scroll_y };
+        self.dragging = true;
+        self.drag_delta = (self.drag_delta.0 + dx, self.drag_delta.1 + dy);
+        self.set_offset(x, y)
+    }
+
+    /// The pointer let the container go, which keeps moving with the velocity of the drag
+    pub fn release(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Advance the momentum and bounce by `dt` milliseconds, returns whether the items moved
+    pub fn advance_scroll(&mut self, dt: f64) -> bool {
+        let physics = match self.physics {
+            Some(physics) => physics,
+            None => return false,
+        };
+        if self.dragging {
+            if dt > 0. {
+                let (dx, dy) = self.drag_delta;
+                self.velocity = match self.scroll {
+                    Scrollable::X => (dx / dt, 0.),
+                    Scrollable::Y => (0., dy / dt),
+                    Scrollable::None => (0., 0.),
+                };
+                self.drag_delta = (0., 0.);
+            }
+            return false;
+        }
+        let (max_x, max_y) = self.max_scroll();
+        let settled = physics.is_settled(self.scroll_x, self.velocity.0, max_x)
+            && physics.is_settled(self.scroll_y, self.velocity.1, max_y);
+        if settled || dt <= 0. {
+            return false;
+        }
+        let (x, vx) = physics.step(self.scroll_x, self.velocity.0, max_x, dt);
+        let (y, vy) = physics.step(self.scroll_y, self.velocity.1, max_y, dt);
+        self.velocity = (vx, vy);
+        self.set_offset(x, y) != (0., 0.)
+    }
+
+    /// Scroll the item called `name` into view, looking into the sections below too,
+    /// returns false if it is not below the container
+    pub fn scroll_into_view(&mut self, name: &str) -> bool {
+        let mut found = None;
+        for item in self.inventory.iter() {
+            let inside = match item {
+                Content::Section { ref section } => section.borrow_mut().scroll_into_view(name),
+                Content::Span { .. } => false,
+            };
+            if inside || item.get_name() == name {
+                found = Some(item.get_rect());
+                break;
+            }
+        }
+        match found {
+            Some(rect) => {
+                if let Some(rect) = rect {
+                    self.reveal(&rect);
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Scroll the least needed for the area to be visible, its top left first
+    fn reveal(&mut self, rect: &Rect) {
+        let (view_w, view_h) = (self.right - self.left, self.bottom - self.top);
+        // Offset of the area within the items
+        let (item_x, item_y) = (rect.x - self.left, rect.y - self.top);
+        let x = self.scroll_x.max(item_x + rect.w - view_w).min(item_x);
+        let y = self.scroll_y.max(item_y + rect.h - view_h).min(item_y);
+        self.scroll_to(x, y);
+    }
+
+    fn get_rect(&self) -> Rect {
+        Rect::new(self.left, self.top, self.right - self.left, self.bottom - self.top)
     }
 
     /// Deprecated
@@ -109,15 +319,85 @@ impl Container {
     }
 
     pub fn render_tick(&self, ctx: &RendererContext) {
-        self.draw_outline(ctx);
+        if ctx.is_layer(layer::DEFAULT_LAYER) {
+            self.draw_outline(ctx);
+        }
+        // Items of scrolling containers are clipped to its area and drawn moved by the offset
+        let scrolling = self.is_scrollable() && ctx.context_gl.is_none();
+        if !scrolling {
+            for item in self.inventory.iter() {
+                // Items without a known area are always drawn
+                let shown = item.get_rect().map(|rect| ctx.should_render(&rect));
+                if item.is_visible() && shown.unwrap_or(true) {
+                    item.render_tick(ctx);
+                }
+            }
+            return;
+        }
+        let area = self.get_rect();
+        let (dx, dy) = (self.scroll_x, self.scroll_y);
+        let canvas = &ctx.context_2d;
+        canvas.save();
+        canvas.begin_path();
+        canvas.rect(area.x, area.y, area.w, area.h);
+        canvas.clip();
+        canvas.translate(-dx, -dy);
+        // The visible area and the region to redraw, where the items are laid out
+        let view = Rect::new(area.x + dx, area.y + dy, area.w, area.h);
+        let context = RendererContext { region: ctx.region.map(|r| Rect::new(r.x + dx, r.y + dy, r.w, r.h)), ..ctx.clone() };
+        for item in self.inventory.iter() {
+            let shown = item.get_rect().map(|rect| context.should_render(&rect) && rect.intersects(&view));
+            if item.is_visible() && shown.unwrap_or(true) {
+                item.render_tick(&context);
+            }
+        }
+        canvas.translate(dx, dy);
+        if ctx.is_layer(layer::DEFAULT_LAYER) {
+            self.draw_scrollbar(ctx);
+        }
+        canvas.restore();
+    }
+
+    /// Append where the region, invalidated by items laid out below the container, shows
+    /// once moved by the offsets of the scrolling containers around them. `offset` is the
+    /// sum of the offsets of the containers around this one.
+    pub fn scrolled_damage(&self, region: &Rect, offset: (f64, f64), damage: &mut Vec<Rect>) {
+        let (dx, dy) = (offset.0 + self.scroll_x, offset.1 + self.scroll_y);
+        if self.is_scrollable() && (self.scroll_x, self.scroll_y) != (0., 0.) {
+            let shown = Rect::new(region.x - dx, region.y - dy, region.w, region.h);
+            let view = Rect::new(self.left - offset.0, self.top - offset.1, self.right - self.left, self.bottom - self.top);
+            if shown.intersects(&view) {
+                damage.push(shown);
+            }
+        }
         for item in self.inventory.iter() {
-            item.render_tick(ctx);
+            if let Content::Section { ref section } = item {
+                section.borrow().scrolled_damage(region, (dx, dy), damage);
+            }
+        }
+    }
+
+    // Thin bar along the scrolling edge, sized and placed after the visible part of the items
+    fn draw_scrollbar(&self, ctx: &RendererContext) {
+        let (w, h) = (self.right - self.left, self.bottom - self.top);
+        let canvas = &ctx.context_2d;
+        canvas.set_fill_style("#07ce88");
+        match self.scroll {
+            Scrollable::X if self.content_w > w => {
+                let length = w * w / self.content_w;
+                canvas.fill_rect(self.left + self.scroll_x * w / self.content_w, self.bottom - SCROLLBAR_WIDTH, length, SCROLLBAR_WIDTH);
+            },
+            Scrollable::Y if self.content_h > h => {
+                let length = h * h / self.content_h;
+                canvas.fill_rect(self.right - SCROLLBAR_WIDTH, self.top + self.scroll_y * h / self.content_h, SCROLLBAR_WIDTH, length);
+            },
+            _ => {},
         }
     }
 
-    pub fn tick(&mut self) {
+    pub fn tick(&mut self, time: &FrameTime) {
         for item in self.inventory.iter_mut() {
-            item.tick();
+            item.tick(time);
         }
     }
 
@@ -156,15 +436,77 @@ impl Container {
         self.inventory.sort_by_key(|i| i.get_order_value());
     }
 
-    fn consume_event(&mut self, _ev: &mut Event) {
+    /// Remove the item called `name` from the container, without laying out the others again
+    pub fn remove(&mut self, name: &str) -> Option<Content> {
+        let index = self.inventory.iter().position(|item| item.get_name() == name)?;
+        Some(self.inventory.remove(index))
+    }
+
+    /// Where the position falls among the items, as laid out before scrolling
+    pub fn content_pos(&self, pos: &Position) -> Position {
+        Position::new(pos.x() + self.scroll_x, pos.y() + self.scroll_y)
     }
 
-    pub fn dispatch_event(&mut self, ev: &mut Event) {
-        if ev.pos.in_area(self.left, self.top, self.right, self.bottom) {
-            for item in self.inventory.iter_mut() {
-                item.dispatch_event(ev);
+    /// Items under the position, topmost first
+    pub fn hit_items(&self, pos: &Position) -> Vec<&Content> {
+        if !pos.in_area(self.left, self.top, self.right, self.bottom) {
+            return Vec::new();
+        }
+        let pos = self.content_pos(pos);
+        // Later items of the same order are drawn over the earlier ones
+        self.inventory.iter().rev().filter(|item| item.is_visible() && item.contains(&pos)).collect()
+    }
+
+    /// Append the topmost item under the position to `path`, then the items under it
+    /// within that item when it is a section
+    pub fn hit_path(&self, pos: &Position, path: &mut Vec<Content>) {
+        if let Some(item) = self.hit_items(pos).first() {
+            path.push((*item).clone());
+            if let Content::Section { ref section } = item {
+                section.borrow().hit_path(&self.content_pos(pos), path);
             }
-            self.consume_event(ev);
+        }
+    }
+
+    /// Append the paths to the focusable spans below the container to `paths`, each made of
+    /// `path`, the sections leading to the span and the span itself
+    pub fn focusable_paths(&self, path: &mut Vec<Content>, paths: &mut Vec<Vec<Content>>) {
+        for item in self.inventory.iter() {
+            match item {
+                Content::Section { ref section } => {
+                    if !item.is_visible() {
+                        continue;
+                    }
+                    path.push(item.clone());
+                    section.borrow().focusable_paths(path, paths);
+                    path.pop();
+                },
+                Content::Span { .. } => {
+                    if item.is_focusable() {
+                        let mut focusable = path.clone();
+                        focusable.push(item.clone());
+                        paths.push(focusable);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Append the sections an event at the position passes through to `path`, outermost
+    /// first, and return the spans it targets within the innermost one, topmost first.
+    ///
+    /// The topmost section under the pointer takes the event down its own subtree,
+    /// otherwise every span under it is a target. Nothing stays borrowed, so listeners
+    /// run along the route are free to update any section or span.
+    pub fn event_route(&self, pos: &Position, path: &mut Vec<Content>) -> Vec<Content> {
+        let hit = self.hit_items(pos);
+        match hit.first() {
+            None => Vec::new(),
+            Some(Content::Section { ref section }) => {
+                path.push(hit[0].clone());
+                section.borrow().event_route(&self.content_pos(pos), path)
+            },
+            Some(Content::Span { .. }) => hit.into_iter().filter(|item| item.is_span()).cloned().collect(),
         }
     }
 
@@ -2,6 +2,10 @@ use crate::section::*;
 use crate::component::*;
 use crate::span::*;
 use crate::prelude::renderer::RendererContext;
+use crate::core::{State, StateProto};
+use crate::drag::DragPayload;
+
+use std::rc::Rc;
 
 pub enum Alignment {
     Left,
@@ -10,6 +14,7 @@ pub enum Alignment {
     Bottom,
 }
 
+#[derive(Clone)]
 pub enum Content {
     Section {
         section: SectionRef,
@@ -29,8 +34,7 @@ impl Content {
             Content::Span { ref span } => {
                 let mut span = span.borrow_mut();
                 let span = span.as_mut();
-                span.on_resize(left, top, right, bottom);
-                (0., 0., true)
+                span.on_resize(left, top, right, bottom)
             }
         }
     }
@@ -58,45 +62,132 @@ impl Content {
             },
             Content::Span { ref span } => {
                 let span = span.borrow();
-                span.render_tick(ctx);
+                if ctx.is_layer(span.get_layer()) {
+                    span.render_tick(ctx);
+                }
             }
         }
     }
 
-    pub fn tick(&mut self) {
+    pub fn tick(&mut self, time: &FrameTime) {
         match self {
             Content::Section { ref mut section } => {
                 let mut section = section.borrow_mut();
-                section.tick();
+                section.tick(time);
             },
             Content::Span { ref mut span } => {
                 let mut span = span.borrow_mut();
-                span.tick();
+                span.tick(time);
             }
         }
     }
 
-    pub fn get_order_value(&self) -> u8 {
+    /// Area occupied by the item, `None` if unknown
+    pub fn get_rect(&self) -> Option<Rect> {
+        match self {
+            Content::Section { ref section } => Some(section.borrow().get_rect()),
+            Content::Span { ref span } => span.borrow().get_rect(),
+        }
+    }
+
+    /// Whether the position is over the item, items without a known area cover their container
+    pub fn contains(&self, pos: &Position) -> bool {
+        self.get_rect().map(|rect| pos.in_rec(rect.x, rect.y, rect.w, rect.h)).unwrap_or(true)
+    }
+
+    /// Whether both refer to the same section or span
+    pub fn ptr_eq(&self, other: &Content) -> bool {
+        match (self, other) {
+            (Content::Section { section: a }, Content::Section { section: b }) => Rc::ptr_eq(a, b),
+            (Content::Span { span: a }, Content::Span { span: b }) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        match self {
+            Content::Section { ref section } => section.borrow().name.clone(),
+            Content::Span { ref span } => span.borrow().get_name().to_string(),
+        }
+    }
+
+    /// Deliver the event to the item alone, in the given phase.
+    ///
+    /// Listeners run once the item is released, so they can update it, the caller must not
+    /// hold a borrow of any section either.
+    pub fn deliver_event(&self, ev: &mut Event, phase: EventPhase, state: &State) {
+        ev.phase = phase;
         match self {
             Content::Section { ref section } => {
-                section.borrow().order
+                let name = {
+                    let mut section = section.borrow_mut();
+                    section.receive_event(ev);
+                    section.name.clone()
+                };
+                StateProto::emit(state, &name, ev);
             },
             Content::Span { ref span } => {
-                span.borrow().get_order()
-            }
+                let name = {
+                    let mut span = span.borrow_mut();
+                    let span = span.as_mut();
+                    span.dispatch_event(ev);
+                    span.get_name().to_string()
+                };
+                StateProto::emit(state, &name, ev);
+            },
         }
     }
 
-    pub fn dispatch_event(&mut self, ev: &mut Event) {
+    /// Payload to carry when dragging the item, only spans can be dragged
+    pub fn drag_payload(&self) -> Option<DragPayload> {
+        match self {
+            Content::Section { .. } => None,
+            Content::Span { ref span } => span.borrow().drag_payload(),
+        }
+    }
+
+    /// Whether the item is a drop target for the payload
+    pub fn accepts_drop(&self, payload: &DragPayload) -> bool {
+        match self {
+            Content::Section { ref section } => section.borrow().accepts_drop(payload),
+            Content::Span { ref span } => {
+                let span = span.borrow();
+                span.is_visible() && span.accepts_drop(payload)
+            },
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        match self {
+            Content::Section { ref section } => section.borrow().is_visible(),
+            Content::Span { ref span } => span.borrow().is_visible(),
+        }
+    }
+
+    pub fn is_focusable(&self) -> bool {
+        match self {
+            Content::Section { .. } => false,
+            Content::Span { ref span } => {
+                let span = span.borrow();
+                span.is_visible() && span.is_focusable()
+            },
+        }
+    }
+
+    pub fn is_span(&self) -> bool {
+        match self {
+            Content::Span { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn get_order_value(&self) -> u8 {
         match self {
             Content::Section { ref section } => {
-                let mut section = section.borrow_mut();
-                section.dispatch_event(ev);
+                section.borrow().order
             },
             Content::Span { ref span } => {
-                let mut span = span.borrow_mut();
-                let span = span.as_mut();
-                span.dispatch_event(ev);
+                span.borrow().get_order()
             }
         }
     }
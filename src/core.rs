@@ -1,26 +1,168 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::collections::HashMap;
 
 use crate::scene::Scene;
 use crate::component::*;
 use crate::section::*;
+use crate::content::Content;
 use crate::container::Container;
 use crate::span::*;
 use crate::utils;
 use crate::input::*;
+use crate::canvas::Canvas2D;
+use crate::transition::{Transition, ActiveTransition};
+use crate::layer::{self, Layer, LayerKind, Surface};
+use crate::replay::{InputEvent, InputLog};
+use crate::drag::{self, DragPayload, DragState};
+use crate::gesture::{Gesture, GestureRecognizer, Touch};
 use crate::prelude::{renderer, js::{self, JsCast}};
 
 
+/// Canvas size in logical (CSS) pixels, which all layout works in
 pub struct CanvasMeta {
     pub w: u32,
     pub h: u32,
+    /// Device pixels per logical pixel
+    pub ratio: f64,
 }
 
+impl CanvasMeta {
+    pub fn new(w: u32, h: u32) -> Self {
+        Self::with_ratio(w, h, 1.)
+    }
+
+    pub fn with_ratio(w: u32, h: u32, ratio: f64) -> Self {
+        Self { w, h, ratio }
+    }
+
+    /// Width of the backing store in device pixels
+    pub fn device_width(&self) -> u32 {
+        (self.w as f64 * self.ratio).round() as u32
+    }
+
+    /// Height of the backing store in device pixels
+    pub fn device_height(&self) -> u32 {
+        (self.h as f64 * self.ratio).round() as u32
+    }
+}
+
+/// Areas of the canvas to redraw on the next frame
+#[derive(Clone, PartialEq, Debug)]
+pub enum Damage {
+    None,
+    Full,
+    Regions(Vec<Rect>),
+}
+
+// Above this many separate regions a full redraw is cheaper
+const MAX_DIRTY_REGIONS: usize = 16;
+
+type ListenerFn = Rc<RefCell<dyn FnMut(&mut Event)>>;
+
+struct Listener {
+    id: u64,
+    kind: EventKind,
+    capture: bool,
+    callback: ListenerFn,
+}
+
+/// Returned by `StateProto::on`, pass it to `StateProto::off` to remove the listener
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ListenerHandle {
+    name: String,
+    id: u64,
+}
+
+/// Invalidated areas of the canvas, shared by the state and anything drawing into it.
+///
+/// Spans and sections keep a handle to it, so they can mark themselves dirty while
+/// application code holds the state, even mutably.
+pub struct DirtyTracker {
+    dirty: RefCell<Vec<Rect>>,
+    full_redraw: Cell<bool>,
+    // Every invalidated area regardless of full redraws, for cached layers to check
+    changed: RefCell<Vec<Rect>>,
+    frame_changed: RefCell<Vec<Rect>>,
+}
+
+impl DirtyTracker {
+    pub fn new() -> Dirty {
+        Rc::new(DirtyTracker {
+            dirty: RefCell::new(Vec::new()),
+            full_redraw: Cell::new(true),
+            changed: RefCell::new(Vec::new()),
+            frame_changed: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Mark an area of the canvas to be redrawn on the next frame
+    pub fn invalidate(&self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        self.changed.borrow_mut().push(rect);
+        if !self.full_redraw.get() {
+            self.dirty.borrow_mut().push(rect);
+        }
+    }
+
+    /// Whether an area invalidated before the frame being rendered touches `rect`
+    pub fn was_invalidated(&self, rect: &Rect) -> bool {
+        self.frame_changed.borrow().iter().any(|r| r.intersects(rect))
+    }
+
+    /// Redraw the whole canvas on the next frame
+    pub fn invalidate_all(&self) {
+        self.full_redraw.set(true);
+        self.dirty.borrow_mut().clear();
+    }
+
+    /// Take the pending damage, merging overlapping regions
+    pub fn take_damage(&self) -> Damage {
+        self.frame_changed.replace(self.changed.replace(Vec::new()));
+        if self.full_redraw.replace(false) {
+            self.dirty.borrow_mut().clear();
+            return Damage::Full;
+        }
+        let mut pending = self.dirty.replace(Vec::new());
+        if pending.is_empty() {
+            return Damage::None;
+        }
+
+        let mut regions: Vec<Rect> = Vec::new();
+        while let Some(mut rect) = pending.pop() {
+            // Merge with every known region it touches, rechecking after each growth
+            let mut merged = true;
+            while merged {
+                merged = false;
+                if let Some(i) = regions.iter().position(|r| r.intersects(&rect)) {
+                    rect = rect.union(&regions.swap_remove(i));
+                    merged = true;
+                }
+            }
+            regions.push(rect);
+        }
+        if regions.len() > MAX_DIRTY_REGIONS {
+            Damage::Full
+        } else {
+            Damage::Regions(regions)
+        }
+    }
+}
+
+pub type Dirty = Rc<DirtyTracker>;
+
 pub struct StateProto {
     // store: HashMap::<String, Box<dyn Any>>
     sections: HashMap::<String, SectionWeak>,
     spans: HashMap::<String, SpanWeak>,
+
+    dirty: Dirty,
+
+    // Event listeners by section or span name
+    listeners: RefCell<HashMap<String, Vec<Listener>>>,
+    next_listener: Cell<u64>,
 }
 
 impl StateProto {
@@ -28,9 +170,37 @@ impl StateProto {
         Rc::new(RefCell::new(StateProto {
             sections: HashMap::new(),
             spans: HashMap::new(),
+            dirty: DirtyTracker::new(),
+            listeners: RefCell::new(HashMap::new()),
+            next_listener: Cell::new(0),
         }))
     }
 
+    /// Handle to the invalidated areas, usable without borrowing the state
+    pub fn get_dirty(&self) -> Dirty {
+        self.dirty.clone()
+    }
+
+    /// Mark an area of the canvas to be redrawn on the next frame
+    pub fn invalidate(&self, rect: Rect) {
+        self.dirty.invalidate(rect);
+    }
+
+    /// Whether an area invalidated before the frame being rendered touches `rect`
+    pub fn was_invalidated(&self, rect: &Rect) -> bool {
+        self.dirty.was_invalidated(rect)
+    }
+
+    /// Redraw the whole canvas on the next frame
+    pub fn invalidate_all(&self) {
+        self.dirty.invalidate_all();
+    }
+
+    /// Take the pending damage, merging overlapping regions
+    pub fn take_damage(&self) -> Damage {
+        self.dirty.take_damage()
+    }
+
     pub fn register_section(&mut self, section: &SectionRef) {
         let item = section.borrow();
         self.sections.insert(item.name.to_string(), Rc::downgrade(section));
@@ -57,6 +227,60 @@ impl StateProto {
         }
     }
 
+    /// Call `listener` with the `kind` events reaching the section or span called `name`,
+    /// as their target or while bubbling up from it
+    pub fn on<F: 'static + FnMut(&mut Event)>(&self, name: &str, kind: EventKind, listener: F) -> ListenerHandle {
+        self.add_listener(name, kind, false, Rc::new(RefCell::new(listener)))
+    }
+
+    /// Like `on`, for the capture phase going down to the target instead
+    pub fn on_capture<F: 'static + FnMut(&mut Event)>(&self, name: &str, kind: EventKind, listener: F) -> ListenerHandle {
+        self.add_listener(name, kind, true, Rc::new(RefCell::new(listener)))
+    }
+
+    fn add_listener(&self, name: &str, kind: EventKind, capture: bool, callback: ListenerFn) -> ListenerHandle {
+        let id = self.next_listener.get();
+        self.next_listener.set(id + 1);
+        self.listeners.borrow_mut().entry(name.to_string()).or_insert_with(Vec::new)
+            .push(Listener { id, kind, capture, callback });
+        ListenerHandle { name: name.to_string(), id }
+    }
+
+    /// Remove a listener, returns false if it was already removed
+    pub fn off(&self, handle: &ListenerHandle) -> bool {
+        let mut listeners = self.listeners.borrow_mut();
+        let list = match listeners.get_mut(&handle.name) {
+            Some(list) => list,
+            None => return false,
+        };
+        let count = list.len();
+        list.retain(|listener| listener.id != handle.id);
+        count != list.len()
+    }
+
+    /// Run the listeners of `name` for the event in its current phase.
+    ///
+    /// Neither the state nor any section or span may be borrowed by the caller, listeners
+    /// are free to use the state and to update any section or span, including the one they
+    /// listen on. Events are delivered along routes collected beforehand for that reason.
+    pub fn emit(state: &State, name: &str, ev: &mut Event) {
+        let callbacks: Vec<ListenerFn> = {
+            let state = state.borrow();
+            let listeners = state.listeners.borrow();
+            let capture = ev.phase == EventPhase::Capture;
+            match listeners.get(name) {
+                Some(list) => list.iter()
+                    .filter(|listener| listener.kind == ev.ev && listener.capture == capture)
+                    .map(|listener| listener.callback.clone())
+                    .collect(),
+                None => return,
+            }
+        };
+        for callback in callbacks {
+            (&mut *callback.borrow_mut())(ev);
+        }
+    }
+
 }
 
 pub type State = Rc<RefCell<StateProto>>;
@@ -71,7 +295,7 @@ impl FpsCounterProto {
         self.0 += 1;
         if self.0 >= self.1 {
             let now = utils::now_ms();
-            self.3 = 1000 * self.1 as u32 / (now - self.2) as u32;
+            self.3 = 1000 * self.1 as u32 / (now - self.2).max(1) as u32;
             self.2 = now;
             self.0 = 0;
         }
@@ -84,19 +308,100 @@ impl FpsCounterProto {
 
 #[derive(Clone)]
 pub struct RendererContext {
-    pub context_2d: renderer::Context2D,
-    pub context_gl: renderer::ContextGL,
+    pub context_2d: Rc<dyn Canvas2D>,
+    pub context_gl: Option<renderer::ContextGL>,
+    /// Area being redrawn, `None` for the whole canvas
+    pub region: Option<Rect>,
+    /// Device pixels per logical pixel, for offscreen surfaces to match the canvas
+    pub pixel_ratio: f64,
+    /// Name of the layer being drawn
+    pub layer: String,
+}
+
+impl RendererContext {
+    /// Whether this pass draws the layer called `name`
+    pub fn is_layer(&self, name: &str) -> bool {
+        self.layer == name
+    }
+
+    /// Whether something occupying `rect` needs to be drawn in this pass
+    pub fn should_render(&self, rect: &Rect) -> bool {
+        match self.region {
+            Some(ref region) => region.intersects(rect),
+            None => true,
+        }
+    }
+}
+
+/// Called with the previous and the new scene path whenever the active scene changes
+pub type SceneChangeHandler = Box<dyn FnMut(&str, &str)>;
+
+pub type ApplicationRef = Rc<RefCell<Application>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunState {
+    Stopped,
+    Running,
+    Paused,
 }
 
 pub struct Application {
-    document: renderer::Document,
-    canvas: renderer::HtmlCanvasElement, 
-    canvas_gl: renderer::HtmlCanvasElement,
+    // DOM handles, absent for headless applications
+    document: Option<renderer::Document>,
+    canvas: Option<renderer::HtmlCanvasElement>,
+
+    // Surfaces of the scene layers by name, the main canvas being the default layer
+    surfaces: HashMap<String, Surface>,
 
     scenes: HashMap<String, Scene>,
     path: String,
     meta: CanvasMeta,
 
+    // Visited scene paths and the position of the active one
+    history: Vec<String>,
+    history_index: usize,
+    on_scene_change: Option<SceneChangeHandler>,
+
+    // Transition used when navigating and the one currently running
+    transition: Transition,
+    active_transition: Option<ActiveTransition>,
+    last_tick: u128,
+
+    // Timing of the last tick, and the optional fixed simulation step with its backlog
+    time: FrameTime,
+    frames: u64,
+    fixed_step: Option<FixedStep>,
+
+    // Shared with the animation frame closure while the run loop is alive
+    run_state: Rc<Cell<RunState>>,
+    // Generation of the live loop, bumped by `stop` so its closure exits on the next frame
+    run_loop: Rc<Cell<u32>>,
+
+    // Redraw everything each frame instead of the invalidated regions only
+    full_redraw: bool,
+
+    // Items under the pointer as of its last move, outermost first
+    hovered: Vec<Content>,
+    pointer: Position,
+
+    // Focused span last, preceded by the sections leading to it, key handlers only
+    // borrow the application
+    focused: RefCell<Vec<Content>>,
+
+    // Where the primary button was pressed outside of drag sources and the pointer since,
+    // dragging scrolls the container under the press
+    scroll_drag: Option<(Position, Position)>,
+
+    // Press on a drag source, and the drag it turned into once the pointer moved enough
+    drag: Option<DragState>,
+
+    // Touches in contact and the gestures they make, timed by the ticks received
+    gestures: GestureRecognizer,
+    clock: f64,
+
+    // Inputs received since the recording started, with the time it started at
+    recording: RefCell<Option<(u128, InputLog)>>,
+
     state: State,
 
     pub input: Input,
@@ -113,55 +418,217 @@ impl Application {
             .dyn_into::<renderer::HtmlCanvasElement>()
             .map_err(|_| ())
             .unwrap();
-        let canvas_gl = document.get_element_by_id(canvas_gl_id).unwrap();
-        let canvas_gl: renderer::HtmlCanvasElement = canvas_gl
-            .dyn_into::<renderer::HtmlCanvasElement>()
-            .map_err(|_| ())
+
+        let context_2d = canvas.get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<renderer::Context2D>()
             .unwrap();
+        let meta = CanvasMeta::new(canvas.width(), canvas.height());
+        // The world canvas is optional, scenes declaring a WebGL layer get one created otherwise
+        let canvas_gl = document.get_element_by_id(canvas_gl_id)
+            .and_then(|canvas| canvas.dyn_into::<renderer::HtmlCanvasElement>().ok());
+        let context_gl = canvas_gl.as_ref().and_then(Self::get_context_gl);
+        let context = RendererContext {
+            context_2d: Rc::new(context_2d),
+            context_gl: context_gl.clone(),
+            region: None,
+            pixel_ratio: 1.,
+            layer: layer::DEFAULT_LAYER.to_string(),
+        };
+        let mut app = Self::new_with_context(context, meta);
+        if let (Some(canvas_gl), Some(context_gl)) = (canvas_gl, context_gl) {
+            app.surfaces.insert(layer::WORLD_LAYER.to_string(), Surface::new_gl(context_gl).with_canvas(canvas_gl));
+        }
+        app.document = Some(document);
+        app.surfaces.get_mut(layer::DEFAULT_LAYER).unwrap().canvas = Some(canvas.clone());
+        app.canvas = Some(canvas);
+        app.update_canvas_meta();
+        app.on_resize();
+        app
+    }
+
+    /// Create an application without any DOM, rendering into the provided 2d backend.
+    ///
+    /// Layout, event dispatch, ticking and rendering all work natively, which makes it
+    /// usable in plain `cargo test` with a `NullCanvas` or any other `Canvas2D`.
+    pub fn new_headless(meta: CanvasMeta, context_2d: Rc<dyn Canvas2D>) -> Self {
+        let context = RendererContext {
+            context_2d,
+            context_gl: None,
+            region: None,
+            pixel_ratio: meta.ratio,
+            layer: layer::DEFAULT_LAYER.to_string(),
+        };
+        let mut app = Self::new_with_context(context, meta);
+        app.on_resize();
+        app
+    }
 
+    fn new_with_context(context: RendererContext, meta: CanvasMeta) -> Self {
         let mut scenes = HashMap::new();
         let state = StateProto::new();
         // Fill in the default scene
         let scene = Scene::default(state.clone());
         let path = scene.path.clone();
         scenes.insert(scene.path.clone(), scene);
-        let context_2d = canvas.get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<renderer::Context2D>()
-            .unwrap();
-        let meta = CanvasMeta { w: canvas.width(), h: canvas.height() };
-        let context_gl = canvas_gl.get_context("webgl")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<renderer::ContextGL>()
-            .unwrap();
-        let mut app = Self {
-            document,
-            canvas,
-            canvas_gl,
+        let mut surfaces = HashMap::new();
+        surfaces.insert(layer::DEFAULT_LAYER.to_string(), Surface::new_2d(context.context_2d.clone()));
+        Self {
+            document: None,
+            canvas: None,
+            surfaces,
             scenes,
+            history: vec![path.clone()],
+            history_index: 0,
+            on_scene_change: None,
+            transition: Transition::none(),
+            active_transition: None,
+            last_tick: utils::now_ms(),
+            time: FrameTime::default(),
+            frames: 0,
+            fixed_step: None,
+            run_state: Rc::new(Cell::new(RunState::Stopped)),
+            run_loop: Rc::new(Cell::new(0)),
+            full_redraw: false,
+            hovered: Vec::new(),
+            pointer: Position::default(),
+            focused: RefCell::new(Vec::new()),
+            scroll_drag: None,
+            drag: None,
+            gestures: GestureRecognizer::new(),
+            clock: 0.,
+            recording: RefCell::new(None),
             path,
-            context: RendererContext { context_2d, context_gl },
+            context,
             meta,
             state,
             input: InputProto::new(),
             counter: FpsCounterProto::new(10),
-        };
-        app.update_canvas_meta();
-        app.on_resize();
-        app
+        }
     }
 
-    pub fn register(&mut self, scene: Scene) {
+    /// Register a scene, returns false if a scene with the same path already exists.
+    ///
+    /// The first registered scene replaces the default one and becomes active,
+    /// later ones are only activated through `navigate`.
+    pub fn register(&mut self, scene: Scene) -> bool {
         // If register with custom scenes the default scene will be removed
-        if self.path.is_empty() {
+        let replace_default = self.path.is_empty();
+        if replace_default {
             self.scenes.clear();
         }
         let path = scene.path.clone();
-        if self.scenes.get(&path).is_none() {
-            self.scenes.insert(scene.path.clone(), scene);
-            self.path = path;
+        if self.scenes.contains_key(&path) {
+            warn!("Scene {} is already registered", path);
+            return false;
+        }
+        self.scenes.insert(path.clone(), scene);
+        if replace_default {
+            self.history = vec![path.clone()];
+            self.history_index = 0;
+            self.switch_scene(&path, Transition::none());
+        }
+        true
+    }
+
+    /// Activate the scene registered with `path`, dropping any forward history
+    pub fn navigate(&mut self, path: &str) -> bool {
+        let transition = self.transition;
+        self.navigate_with(path, transition)
+    }
+
+    /// Like `navigate`, animating with `transition` instead of the default one
+    pub fn navigate_with(&mut self, path: &str, transition: Transition) -> bool {
+        if !self.scenes.contains_key(path) {
+            warn!("Scene {} is not registered", path);
+            return false;
+        }
+        if path == self.path {
+            return true;
+        }
+        self.history.truncate(self.history_index + 1);
+        self.history.push(path.to_string());
+        self.history_index = self.history.len() - 1;
+        self.switch_scene(path, transition);
+        true
+    }
+
+    pub fn back(&mut self) -> bool {
+        if self.history_index == 0 {
+            return false;
+        }
+        self.history_index -= 1;
+        let path = self.history[self.history_index].clone();
+        self.switch_scene(&path, self.transition);
+        true
+    }
+
+    pub fn forward(&mut self) -> bool {
+        if self.history_index + 1 >= self.history.len() {
+            return false;
+        }
+        self.history_index += 1;
+        let path = self.history[self.history_index].clone();
+        self.switch_scene(&path, self.transition);
+        true
+    }
+
+    /// Set the transition used by `navigate`, `back` and `forward`
+    pub fn set_transition(&mut self, transition: Transition) {
+        self.transition = transition;
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.active_transition.is_some()
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.history_index > 0
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.history_index + 1 < self.history.len()
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_scene(&self, path: &str) -> Option<&Scene> {
+        self.scenes.get(path)
+    }
+
+    pub fn set_on_scene_change<F: 'static + FnMut(&str, &str)>(&mut self, handler: F) {
+        self.on_scene_change = Some(Box::new(handler));
+    }
+
+    fn switch_scene(&mut self, path: &str, transition: Transition) {
+        // Items of the previous scene are no longer under the pointer
+        if self.path != path {
+            self.cancel_drag();
+            self.end_scroll_drag();
+            self.clear_hover();
+            self.blur();
+        }
+        let previous = std::mem::replace(&mut self.path, path.to_string());
+        self.ensure_surfaces();
+        // Only the active scene is kept in size, so catch up before its first frame
+        if let Some(scene) = self.scenes.get_mut(path) {
+            scene.on_resize(&self.meta);
+        }
+        self.state.borrow().invalidate_all();
+        // Input goes to the new scene right away, the old one is only drawn while leaving
+        self.active_transition = if transition.is_none() || previous == path || !self.scenes.contains_key(&previous) {
+            None
+        } else {
+            Some(ActiveTransition::new(&previous, transition))
+        };
+        if previous != path {
+            if let Some(mut handler) = self.on_scene_change.take() {
+                handler(&previous, path);
+                self.on_scene_change = Some(handler);
+            }
         }
     }
 
@@ -179,19 +646,334 @@ impl Application {
             .expect("Failed to register `requestAnimationFrame`");
     }
 
+    /// Redraw the areas invalidated since the previous frame
     pub fn render_tick(&self) {
-        // Clear first?
-        self.context.context_2d.clear_rect(0., 0., self.canvas.width() as f64, self.canvas.height() as f64);
+        let damage = {
+            let state = self.state.borrow();
+            if self.full_redraw || self.active_transition.is_some() {
+                state.invalidate_all();
+            }
+            state.take_damage()
+        };
+        let damage = match damage {
+            Damage::None => return,
+            Damage::Full => Damage::Full,
+            Damage::Regions(mut regions) => {
+                // Items moved by scrolling containers show away from where they are laid out
+                let scene = self.scenes.get(&self.path).unwrap();
+                for i in 0..regions.len() {
+                    let scrolled = scene.scrolled_damage(&regions[i]);
+                    regions.extend(scrolled);
+                }
+                Damage::Regions(regions)
+            },
+        };
+
+        let contexts = self.layer_contexts();
+        // Layers may share a surface, which must then be cleared only once
+        let mut canvases: Vec<Rc<dyn Canvas2D>> = Vec::new();
+        for context in contexts.iter().filter(|context| context.context_gl.is_none()) {
+            if !canvases.iter().any(|canvas| Rc::ptr_eq(canvas, &context.context_2d)) {
+                canvases.push(context.context_2d.clone());
+            }
+        }
+
+        if damage == Damage::Full {
+            // Surfaces of layers no longer on screen would keep showing what was drawn last
+            for canvas in self.stale_canvases(&canvases) {
+                canvas.clear_rect(0., 0., self.meta.device_width() as f64, self.meta.device_height() as f64);
+            }
+        }
+
+        // The drag ghost goes over everything, on the topmost 2D layer
+        let top = contexts.iter().rposition(|context| context.context_gl.is_none());
+        let scaled = self.meta.ratio != 1.;
+        for canvas in canvases.iter().filter(|_| scaled) {
+            canvas.save();
+            canvas.scale(self.meta.ratio, self.meta.ratio);
+        }
+        match damage {
+            Damage::None => {},
+            Damage::Full => {
+                for canvas in canvases.iter() {
+                    canvas.clear_rect(0., 0., self.meta.w as f64, self.meta.h as f64);
+                }
+                for (i, context) in contexts.iter().enumerate() {
+                    self.render_scenes(context);
+                    if Some(i) == top {
+                        self.render_drag_ghost(context);
+                    }
+                }
+            },
+            Damage::Regions(regions) => {
+                for region in regions {
+                    // Strokes straddle the edges of what they outline
+                    let region = region.inflate(1.);
+                    for canvas in canvases.iter() {
+                        canvas.save();
+                        canvas.begin_path();
+                        canvas.rect(region.x, region.y, region.w, region.h);
+                        canvas.clip();
+                        canvas.clear_rect(region.x, region.y, region.w, region.h);
+                    }
+                    for (i, context) in contexts.iter().enumerate() {
+                        let context = RendererContext { region: Some(region), ..context.clone() };
+                        self.render_scenes(&context);
+                        if Some(i) == top {
+                            self.render_drag_ghost(&context);
+                        }
+                    }
+                    for canvas in canvases.iter() {
+                        canvas.restore();
+                    }
+                }
+            }
+        }
+        for canvas in canvases.iter().filter(|_| scaled) {
+            canvas.restore();
+        }
+    }
+
+    // Draw one layer of the active scene, along with the leaving one during a transition
+    fn render_scenes(&self, ctx: &RendererContext) {
         let scene = self.scenes.get(&self.path).unwrap();
-        scene.render_tick(&self.context);
+        if let Some(ref transition) = self.active_transition {
+            if let Some(from) = self.scenes.get(&transition.from) {
+                transition.render_tick(from, scene, ctx, self.meta.w as f64, self.meta.h as f64);
+                return;
+            }
+        }
+        scene.render_tick(ctx);
+    }
+
+    // Draw a translucent copy of the dragged span at the pointer
+    fn render_drag_ghost(&self, ctx: &RendererContext) {
+        let drag = match self.drag {
+            Some(ref drag) if drag.active => drag,
+            _ => return,
+        };
+        let rect = drag.ghost_rect();
+        if !ctx.should_render(&rect) {
+            return;
+        }
+        let (dx, dy) = drag.offset();
+        let canvas = &ctx.context_2d;
+        canvas.save();
+        canvas.set_global_alpha(drag::GHOST_ALPHA);
+        if drag.rect.is_some() {
+            // The source draws where it is laid out, before scrolling
+            let (dx, dy) = (dx - drag.scroll.0, dy - drag.scroll.1);
+            canvas.translate(dx, dy);
+            // The region is in untranslated coordinates, the clip already limits drawing
+            let context = RendererContext { region: None, layer: layer::DEFAULT_LAYER.to_string(), ..ctx.clone() };
+            drag.source.render_tick(&context);
+            canvas.translate(-dx, -dy);
+        }
+        canvas.set_stroke_style("#07ce88");
+        canvas.stroke_rect(rect.x, rect.y, rect.w, rect.h);
+        canvas.restore();
+    }
+
+    // Layers drawn this frame in stacking order, skipping the ones without a surface
+    fn frame_layers(&self) -> Vec<Layer> {
+        let mut layers = self.scenes.get(&self.path).map(|scene| scene.get_layers().to_vec()).unwrap_or_default();
+        if let Some(from) = self.active_transition.as_ref().and_then(|transition| self.scenes.get(&transition.from)) {
+            for layer in from.get_layers() {
+                if !layers.iter().any(|l| l.name == layer.name) {
+                    layers.push(layer.clone());
+                }
+            }
+        }
+        layers
+    }
+
+    // 2D surfaces of the layers not drawn this frame, except the ones shared with `drawn`
+    fn stale_canvases(&self, drawn: &[Rc<dyn Canvas2D>]) -> Vec<Rc<dyn Canvas2D>> {
+        let layers = self.frame_layers();
+        let mut stale: Vec<Rc<dyn Canvas2D>> = Vec::new();
+        for (name, surface) in self.surfaces.iter() {
+            if layers.iter().any(|layer| &layer.name == name) {
+                continue;
+            }
+            if let Some(ref canvas) = surface.context_2d {
+                if !drawn.iter().chain(stale.iter()).any(|other| Rc::ptr_eq(other, canvas)) {
+                    stale.push(canvas.clone());
+                }
+            }
+        }
+        stale
+    }
+
+    fn layer_contexts(&self) -> Vec<RendererContext> {
+        self.frame_layers().iter().filter_map(|layer| self.get_layer_context(&layer.name)).collect()
+    }
+
+    /// Context drawing to the surface of the layer called `name`, for spans that keep one
+    pub fn get_layer_context(&self, name: &str) -> Option<RendererContext> {
+        let surface = self.surfaces.get(name)?;
+        Some(RendererContext {
+            context_2d: surface.context_2d.clone().unwrap_or_else(|| self.context.context_2d.clone()),
+            context_gl: surface.context_gl.clone(),
+            layer: name.to_string(),
+            ..self.context.clone()
+        })
+    }
+
+    /// Back a layer with `surface`, replacing any previous one
+    pub fn set_surface(&mut self, name: &str, surface: Surface) {
+        self.surfaces.insert(name.to_string(), surface);
+        self.stack_surfaces();
+        self.state.borrow().invalidate_all();
+    }
+
+    pub fn get_surface(&self, name: &str) -> Option<&Surface> {
+        self.surfaces.get(name)
+    }
+
+    // Provide a surface for every layer of the scenes on screen, DOM canvases are
+    // created over the main one while headless 2d layers share the main surface
+    fn ensure_surfaces(&mut self) {
+        for layer in self.frame_layers() {
+            if self.surfaces.contains_key(&layer.name) {
+                continue;
+            }
+            let surface = match (self.create_canvas(), layer.kind) {
+                (Some(canvas), LayerKind::Canvas) => canvas.get_context("2d").ok().flatten()
+                    .and_then(|context| context.dyn_into::<renderer::Context2D>().ok())
+                    .map(|context| Surface::new_2d(Rc::new(context)).with_canvas(canvas)),
+                (Some(canvas), LayerKind::WebGL) => Self::get_context_gl(&canvas)
+                    .map(|context| Surface::new_gl(context).with_canvas(canvas)),
+                (None, LayerKind::Canvas) => Some(Surface::new_2d(self.context.context_2d.clone())),
+                (None, LayerKind::WebGL) => None,
+            };
+            match surface {
+                Some(surface) => { self.surfaces.insert(layer.name.clone(), surface); },
+                None => { warn!("No surface available for layer {}", layer.name); },
+            }
+        }
+        self.stack_surfaces();
+    }
+
+    fn create_canvas(&self) -> Option<renderer::HtmlCanvasElement> {
+        let canvas = self.document.as_ref()?.create_element("canvas").ok()?
+            .dyn_into::<renderer::HtmlCanvasElement>().ok()?;
+        self.canvas.as_ref()?.insert_adjacent_element("afterend", &canvas).ok()?;
+        Some(canvas)
+    }
+
+    fn get_context_gl(canvas: &renderer::HtmlCanvasElement) -> Option<renderer::ContextGL> {
+        canvas.get_context("webgl").ok().flatten()
+            .and_then(|context| context.dyn_into::<renderer::ContextGL>().ok())
     }
 
+    // Keep every DOM canvas over the main one, sized like it and ordered like the active scene
+    // layers, hiding the ones of layers not on screen
+    fn stack_surfaces(&self) {
+        let main = match self.canvas {
+            Some(ref canvas) => canvas,
+            None => return,
+        };
+        let layers = self.frame_layers();
+        for (name, surface) in self.surfaces.iter() {
+            let canvas = match surface.canvas {
+                Some(ref canvas) => canvas,
+                None => continue,
+            };
+            let style = canvas.style();
+            let index = layers.iter().position(|layer| &layer.name == name);
+            if let Some(index) = index {
+                let _ = style.set_property("z-index", &index.to_string());
+            }
+            if canvas == main {
+                continue;
+            }
+            let _ = style.set_property("visibility", if index.is_some() { "visible" } else { "hidden" });
+            let _ = style.set_property("position", "absolute");
+            let _ = style.set_property("left", &format!("{}px", main.offset_left()));
+            let _ = style.set_property("top", &format!("{}px", main.offset_top()));
+            let _ = style.set_property("width", &format!("{}px", self.meta.w));
+            let _ = style.set_property("height", &format!("{}px", self.meta.h));
+            let _ = style.set_property("pointer-events", "none");
+            canvas.set_width(self.meta.device_width());
+            canvas.set_height(self.meta.device_height());
+        }
+    }
+
+    /// Redraw the whole canvas on the next frame
+    pub fn invalidate(&self) {
+        self.state.borrow().invalidate_all();
+    }
+
+    /// Redraw everything each frame instead of only the invalidated regions
+    pub fn set_full_redraw(&mut self, full_redraw: bool) {
+        self.full_redraw = full_redraw;
+    }
+
+    /// Tick with the wall clock time since the previous tick, then render the frame
     pub fn tick(&mut self) {
-        for scene in self.scenes.values_mut() {
-            scene.tick();
+        let now = utils::now_ms();
+        let dt = now.saturating_sub(self.last_tick) as f64;
+        self.last_tick = now;
+        self.tick_with(dt);
+        self.render_tick();
+    }
+
+    /// Tick as if `dt` milliseconds passed, useful for deterministic runs.
+    ///
+    /// With a fixed timestep the scenes are ticked zero or more times with exactly
+    /// that step, carrying the remainder over to the next call. The input snapshot
+    /// advances with each step, so presses are seen by exactly one step.
+    pub fn tick_with(&mut self, dt: f64) {
+        self.record(InputEvent::Tick { dt });
+        self.clock += dt;
+        let gestures = self.gestures.tick(self.clock);
+        self.dispatch_gestures(gestures);
+        self.advance_transition(dt);
+        match self.fixed_step.as_mut() {
+            Some(fixed) => {
+                let (steps, step) = (fixed.advance(dt), fixed.get_step());
+                for _ in 0..steps {
+                    self.tick_scenes(step, true);
+                }
+            },
+            None => self.tick_scenes(dt, false),
         }
         self.counter.borrow_mut().tick();
-        self.render_tick();
+    }
+
+    fn tick_scenes(&mut self, dt: f64, fixed: bool) {
+        // Snapshot the keys and buttons once, every scene ticked below sees the same frame
+        self.input.borrow_mut().advance(dt);
+        let time = FrameTime { dt, elapsed: self.time.elapsed + dt, frame: self.frames, fixed };
+        self.time = time;
+        self.frames += 1;
+        for scene in self.scenes.values_mut() {
+            scene.tick(&time);
+        }
+    }
+
+    /// Tick scenes with a fixed `step` in milliseconds, or with the frame time if `None`
+    pub fn set_fixed_timestep(&mut self, step: Option<f64>) {
+        self.fixed_step = step.filter(|step| *step > 0.).map(FixedStep::new);
+    }
+
+    /// Timing of the last scene tick
+    pub fn get_frame_time(&self) -> FrameTime {
+        self.time
+    }
+
+    /// Advance the running transition by `dt` milliseconds
+    pub fn advance_transition(&mut self, dt: f64) {
+        let done = match self.active_transition {
+            Some(ref mut transition) => transition.advance(dt),
+            None => false,
+        };
+        if done {
+            self.active_transition = None;
+            // Layers only the leaving scene used are not on screen anymore
+            self.stack_surfaces();
+            self.state.borrow().invalidate_all();
+        }
     }
 
     /// Deprecated
@@ -199,18 +981,116 @@ impl Application {
         self.render_tick();
     }
 
-    fn run(&self) {
+    /// Start driving the application with `requestAnimationFrame`.
+    ///
+    /// Every frame calls `tick`, which renders. The closure owns a reference to the
+    /// application until `stop` is called, so keep using the shared `ApplicationRef`.
+    pub fn start(app: &ApplicationRef) {
+        let (run_state, run_loop, generation) = {
+            let mut app = app.borrow_mut();
+            if app.run_state.get() != RunState::Stopped {
+                return;
+            }
+            app.last_tick = utils::now_ms();
+            (app.run_state.clone(), app.run_loop.clone(), app.run_loop.get())
+        };
+        run_state.set(RunState::Running);
+
+        let frame: Rc<RefCell<Option<js::Closure<dyn FnMut()>>>> = Rc::new(RefCell::new(None));
+        let next_frame = frame.clone();
+        let app = app.clone();
+        *frame.borrow_mut() = Some(js::Closure::wrap(Box::new(move || {
+            // A loop stopped before this frame is over, even if another one started since
+            if run_loop.get() != generation {
+                // Drop the closure and with it the application reference
+                let _ = next_frame.borrow_mut().take();
+                return;
+            }
+            if run_state.get() == RunState::Running {
+                app.borrow_mut().tick();
+            }
+            Self::request_animation_frame(next_frame.borrow().as_ref().unwrap());
+        }) as Box<dyn FnMut()>));
+        Self::request_animation_frame(frame.borrow().as_ref().unwrap());
+    }
+
+    /// Stop the run loop, it releases the application on the next animation frame
+    pub fn stop(&self) {
+        if self.run_state.get() != RunState::Stopped {
+            self.run_state.set(RunState::Stopped);
+            self.run_loop.set(self.run_loop.get().wrapping_add(1));
+        }
+    }
+
+    /// Keep the run loop alive without ticking or rendering
+    pub fn pause(&self) {
+        if self.run_state.get() == RunState::Running {
+            self.run_state.set(RunState::Paused);
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.run_state.get() == RunState::Paused {
+            // Do not account the paused time to the next tick
+            self.last_tick = utils::now_ms();
+            self.run_state.set(RunState::Running);
+        }
     }
 
+    pub fn get_run_state(&self) -> RunState {
+        self.run_state.get()
+    }
+
+    // Read the css size and pixel ratio of the canvas, and size its backing store to match.
+    // The css size is pinned first, a canvas without one would otherwise take the size
+    // of its backing store and grow by the pixel ratio on every resize.
     fn update_canvas_meta(&mut self)  {
-        self.meta.w = self.canvas.width();
-        self.meta.h = self.canvas.height();
+        if let Some(ref canvas) = self.canvas {
+            if let Some(window) = renderer::window() {
+                self.meta.ratio = window.device_pixel_ratio();
+            }
+            self.meta.w = canvas.client_width().max(0) as u32;
+            self.meta.h = canvas.client_height().max(0) as u32;
+            let style = canvas.style();
+            let _ = style.set_property("width", &format!("{}px", self.meta.w));
+            let _ = style.set_property("height", &format!("{}px", self.meta.h));
+            canvas.set_width(self.meta.device_width());
+            canvas.set_height(self.meta.device_height());
+        }
+        self.context.pixel_ratio = self.meta.ratio;
+        self.ensure_surfaces();
+    }
+
+    /// Resize the canvas (or the virtual one of a headless application) in logical pixels and relayout
+    pub fn set_size(&mut self, w: u32, h: u32) {
+        if let Some(ref canvas) = self.canvas {
+            let style = canvas.style();
+            let _ = style.set_property("width", &format!("{}px", w));
+            let _ = style.set_property("height", &format!("{}px", h));
+        }
+        self.meta.w = w;
+        self.meta.h = h;
+        self.on_resize();
+    }
+
+    /// Change the device pixel ratio of a headless application and relayout,
+    /// browsers report theirs through `window.devicePixelRatio` on resize
+    pub fn set_pixel_ratio(&mut self, ratio: f64) {
+        self.meta.ratio = ratio;
+        self.on_resize();
     }
 
     pub fn on_resize(&mut self) {
         self.update_canvas_meta();
+        self.record(InputEvent::Resize { w: self.meta.w, h: self.meta.h, ratio: self.meta.ratio });
+        self.state.borrow().invalidate_all();
         let scene = self.scenes.get_mut(&self.path).unwrap();
         scene.on_resize(&self.meta);
+        if let Some(ref transition) = self.active_transition {
+            if let Some(from) = self.scenes.get_mut(&transition.from) {
+                from.on_resize(&self.meta);
+            }
+        }
         /*
         for scene in self.scenes.values_mut() {
             scene.on_resize(&self.meta);
@@ -218,19 +1098,550 @@ impl Application {
         */
     }
 
-    pub fn on_mouse_move(&mut self, x: f64, y: f64) {
-        let pos = Position::new(x, y);
-        let mut ev = Event { ev: EventType::MouseMove, pos, consumed: false };
+    /// Start recording every input the application receives, dropping any ongoing recording
+    pub fn start_recording(&mut self) {
+        *self.recording.get_mut() = Some((utils::now_ms(), InputLog::new()));
+    }
+
+    /// Stop recording, returns the inputs received since `start_recording`
+    pub fn stop_recording(&mut self) -> Option<InputLog> {
+        self.recording.get_mut().take().map(|(_, log)| log)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.borrow().is_some()
+    }
+
+    fn record(&self, event: InputEvent) {
+        if let Some((start, ref mut log)) = *self.recording.borrow_mut() {
+            log.push(utils::now_ms().saturating_sub(start) as f64, event);
+        }
+    }
+
+    /// Feed a recorded input back, as the matching `on_*` handler or `tick_with` would receive it
+    pub fn apply_input(&mut self, event: &InputEvent) {
+        match event.clone() {
+            InputEvent::MouseMove { x, y } => self.on_mouse_move(x, y),
+            InputEvent::MouseDown { x, y, button, modifiers } => self.on_mouse_down(x, y, button, modifiers),
+            InputEvent::MouseUp { x, y, button, modifiers } => self.on_mouse_up(x, y, button, modifiers),
+            InputEvent::Click { x, y, button, modifiers } => self.on_click(x, y, button, modifiers),
+            InputEvent::DoubleClick { x, y, button, modifiers } => self.on_double_click(x, y, button, modifiers),
+            InputEvent::ContextMenu { x, y, modifiers } => self.on_context_menu(x, y, modifiers),
+            InputEvent::Wheel { x, y, delta_x, delta_y, modifiers } => self.on_wheel(x, y, delta_x, delta_y, modifiers),
+            InputEvent::PointerEnter { x, y } => self.on_pointer_enter(x, y),
+            InputEvent::PointerLeave { x, y } => self.on_pointer_leave(x, y),
+            InputEvent::KeyDown { key, modifiers } => { self.on_keydown_with(&key, modifiers); },
+            InputEvent::KeyUp { key, modifiers } => { self.on_keyup_with(&key, modifiers); },
+            InputEvent::TouchStart { id, x, y } => self.on_touch_start(id, x, y),
+            InputEvent::TouchMove { id, x, y } => self.on_touch_move(id, x, y),
+            InputEvent::TouchEnd { id, x, y } => self.on_touch_end(id, x, y),
+            InputEvent::TouchCancel { id } => self.on_touch_cancel(id),
+            InputEvent::Resize { w, h, ratio } => {
+                // DOM canvases read their size back, headless ones take the recorded one
+                self.meta.w = w;
+                self.meta.h = h;
+                self.meta.ratio = ratio;
+                self.on_resize();
+            },
+            InputEvent::Tick { dt } => self.tick_with(dt),
+        }
+    }
+
+    /// Deliver a pointer event to the active scene, returns it with the `consumed` flag updated
+    pub fn dispatch_event(&mut self, mut ev: Event) -> Event {
         let scene = self.scenes.get_mut(&self.path).unwrap();
         scene.dispatch_event(&mut ev);
+        ev.phase = EventPhase::None;
+        ev
+    }
+
+    pub fn on_mouse_move(&mut self, x: f64, y: f64) {
+        self.record(InputEvent::MouseMove { x, y });
+        self.update_hover(x, y);
+        self.dispatch_event(Event::new(EventType::MouseMove, x, y));
+        self.update_drag(x, y);
+        if let Some((origin, last)) = self.scroll_drag {
+            self.scroll_drag = Some((origin, Position::new(x, y)));
+            self.scroll_path(origin.x(), origin.y(), last.x() - x, last.y() - y, true);
+        }
+    }
+
+    /// Scroll the innermost scrolling section under (x, y) able to move that way, or the scene,
+    /// returns whether anything scrolled. Sections scrolled receive a `Scroll` event.
+    pub fn scroll_at(&mut self, x: f64, y: f64, dx: f64, dy: f64) -> bool {
+        self.scroll_path(x, y, dx, dy, false)
+    }
+
+    // Scroll as `scroll_at`, or follow a pointer drag with the kinetic containers pulled
+    // past their edges
+    fn scroll_path(&mut self, x: f64, y: f64, dx: f64, dy: f64, drag: bool) -> bool {
+        let path = self.scenes.get(&self.path).unwrap().hit_path(&Position::new(x, y));
+        for item in path.iter().rev() {
+            if let Content::Section { ref section } = item {
+                let (moved_x, moved_y) = if drag {
+                    section.borrow_mut().drag_scroll_by(dx, dy)
+                } else {
+                    section.borrow_mut().scroll_by(dx, dy)
+                };
+                if (moved_x, moved_y) != (0., 0.) {
+                    let mut ev = Event::new(EventType::Scroll, x, y).with_delta(moved_x, moved_y);
+                    item.deliver_event(&mut ev, EventPhase::Target, &self.state);
+                    return true;
+                }
+            }
+        }
+        let scene = self.scenes.get_mut(&self.path).unwrap();
+        let moved = if drag { scene.drag_scroll_by(dx, dy) } else { scene.scroll_by(dx, dy) };
+        moved != (0., 0.)
+    }
+
+    // Stop the momentum of the scene and the sections under (x, y) while the pointer holds them
+    fn grab_scroll_at(&mut self, x: f64, y: f64) {
+        let path = self.scenes.get(&self.path).unwrap().hit_path(&Position::new(x, y));
+        for item in path.iter() {
+            if let Content::Section { ref section } = item {
+                section.borrow_mut().grab_scroll();
+            }
+        }
+        self.scenes.get_mut(&self.path).unwrap().grab_scroll();
+    }
+
+    // Let the scene and the sections under (x, y) go, with the momentum of the drag
+    fn release_scroll_at(&mut self, x: f64, y: f64) {
+        let path = self.scenes.get(&self.path).unwrap().hit_path(&Position::new(x, y));
+        for item in path.iter() {
+            if let Content::Section { ref section } = item {
+                section.borrow_mut().release_scroll();
+            }
+        }
+        self.scenes.get_mut(&self.path).unwrap().release_scroll();
+    }
+
+    fn end_scroll_drag(&mut self) {
+        if let Some((origin, _)) = self.scroll_drag.take() {
+            self.release_scroll_at(origin.x(), origin.y());
+        }
+    }
+
+    /// Scroll the sections holding the item called `name` so it is visible,
+    /// returns false if the active scene has no such item
+    pub fn scroll_into_view(&mut self, name: &str) -> bool {
+        self.scenes.get_mut(&self.path).unwrap().scroll_into_view(name)
+    }
+
+    /// Whether a span is being dragged
+    pub fn is_dragging(&self) -> bool {
+        self.drag.as_ref().map(|drag| drag.active).unwrap_or(false)
+    }
+
+    /// Payload of the ongoing drag
+    pub fn get_drag_payload(&self) -> Option<DragPayload> {
+        self.drag.as_ref().filter(|drag| drag.active).map(|drag| drag.payload.clone())
+    }
+
+    /// Name of the drop target under the dragged span
+    pub fn get_drop_target(&self) -> Option<String> {
+        self.drag.as_ref().and_then(|drag| drag.target.as_ref()).map(|target| target.get_name())
+    }
+
+    // Remember a press on the innermost drag source under the pointer
+    fn press_drag_source(&mut self, x: f64, y: f64) {
+        let path = self.scenes.get(&self.path).unwrap().hit_path(&Position::new(x, y));
+        let scroll = self.path_scroll(&path);
+        self.drag = path.into_iter().rev()
+            .filter(|item| item.is_span())
+            .find_map(|item| item.drag_payload().map(|payload| (item, payload)))
+            .map(|(source, payload)| DragState::new(source, payload, Position::new(x, y), scroll));
+    }
+
+    // Offset the last item of the path is drawn moved by, summed over the containers around it
+    fn path_scroll(&self, path: &[Content]) -> (f64, f64) {
+        let (mut x, mut y) = self.scenes.get(&self.path).unwrap().get_scroll();
+        for item in path.iter() {
+            if let Content::Section { ref section } = item {
+                let (dx, dy) = section.borrow().get_scroll();
+                x += dx;
+                y += dy;
+            }
+        }
+        (x, y)
+    }
+
+    // Start the drag once the pointer moved far enough, then move the ghost along
+    // and track the drop target under it
+    fn update_drag(&mut self, x: f64, y: f64) {
+        let mut drag = match self.drag.take() {
+            Some(drag) => drag,
+            None => return,
+        };
+        let ghost = drag.ghost_rect();
+        drag.pos = Position::new(x, y);
+        if !drag.active {
+            if !drag.past_threshold() {
+                self.drag = Some(drag);
+                return;
+            }
+            let mut ev = Event::new(EventType::DragStart, x, y).with_payload(drag.payload.clone());
+            drag.source.deliver_event(&mut ev, EventPhase::Target, &self.state);
+            if ev.default_prevented {
+                return;
+            }
+            drag.active = true;
+        }
+        {
+            let state = self.state.borrow();
+            state.invalidate(ghost.inflate(1.));
+            state.invalidate(drag.ghost_rect().inflate(1.));
+        }
+
+        // The innermost item accepting the payload, other than the dragged span
+        let path = self.scenes.get(&self.path).unwrap().hit_path(&Position::new(x, y));
+        let target = path.into_iter().rev()
+            .find(|item| !item.ptr_eq(&drag.source) && item.accepts_drop(&drag.payload));
+        let changed = match (&drag.target, &target) {
+            (Some(a), Some(b)) => !a.ptr_eq(b),
+            (None, None) => false,
+            _ => true,
+        };
+        if changed {
+            if let Some(ref old) = drag.target {
+                let mut ev = Event::new(EventType::DragLeave, x, y).with_payload(drag.payload.clone());
+                old.deliver_event(&mut ev, EventPhase::Target, &self.state);
+            }
+            if let Some(ref new) = target {
+                let mut ev = Event::new(EventType::DragEnter, x, y).with_payload(drag.payload.clone());
+                new.deliver_event(&mut ev, EventPhase::Target, &self.state);
+            }
+            drag.target = target;
+        }
+        if let Some(ref target) = drag.target {
+            let mut ev = Event::new(EventType::DragOver, x, y).with_payload(drag.payload.clone());
+            target.deliver_event(&mut ev, EventPhase::Target, &self.state);
+        }
+        self.drag = Some(drag);
+    }
+
+    // Drop on the target under the pointer, if any, and end the drag
+    fn finish_drag(&mut self, x: f64, y: f64) {
+        let drag = match self.drag.take() {
+            Some(drag) if drag.active => drag,
+            _ => return,
+        };
+        self.state.borrow().invalidate(drag.ghost_rect().inflate(1.));
+        if let Some(ref target) = drag.target {
+            let mut ev = Event::new(EventType::Drop, x, y).with_payload(drag.payload.clone());
+            target.deliver_event(&mut ev, EventPhase::Target, &self.state);
+        }
+        let mut ev = Event::new(EventType::DragEnd, x, y).with_payload(drag.payload.clone());
+        drag.source.deliver_event(&mut ev, EventPhase::Target, &self.state);
+    }
+
+    /// Abandon the ongoing drag without dropping, e.g. when the pointer leaves the canvas
+    pub fn cancel_drag(&mut self) {
+        let drag = match self.drag.take() {
+            Some(drag) if drag.active => drag,
+            _ => return,
+        };
+        let (x, y) = (drag.pos.x(), drag.pos.y());
+        self.state.borrow().invalidate(drag.ghost_rect().inflate(1.));
+        if let Some(ref target) = drag.target {
+            let mut ev = Event::new(EventType::DragLeave, x, y).with_payload(drag.payload.clone());
+            target.deliver_event(&mut ev, EventPhase::Target, &self.state);
+        }
+        let mut ev = Event::new(EventType::DragEnd, x, y).with_payload(drag.payload.clone());
+        drag.source.deliver_event(&mut ev, EventPhase::Target, &self.state);
+    }
+
+    // Diff the items under the pointer against the previous ones, leaving the innermost
+    // items first and entering the outermost first
+    fn update_hover(&mut self, x: f64, y: f64) {
+        self.pointer = Position::new(x, y);
+        let path = self.scenes.get(&self.path).unwrap().hit_path(&Position::new(x, y));
+        let old = std::mem::replace(&mut self.hovered, path);
+        for item in old.iter().rev().filter(|item| !self.hovered.iter().any(|i| i.ptr_eq(item))) {
+            item.deliver_event(&mut Event::new(EventType::PointerLeave, x, y), EventPhase::Target, &self.state);
+        }
+        for item in self.hovered.iter().filter(|item| !old.iter().any(|i| i.ptr_eq(item))) {
+            item.deliver_event(&mut Event::new(EventType::PointerEnter, x, y), EventPhase::Target, &self.state);
+        }
+    }
+
+    fn clear_hover(&mut self) {
+        let (x, y) = (self.pointer.x(), self.pointer.y());
+        for item in std::mem::replace(&mut self.hovered, Vec::new()).iter().rev() {
+            item.deliver_event(&mut Event::new(EventType::PointerLeave, x, y), EventPhase::Target, &self.state);
+        }
+    }
+
+    /// Names of the sections and span under (x, y), outermost first.
+    ///
+    /// At each level the topmost visible item wins, by `order` then registration, and
+    /// items only count within the areas of the sections holding them.
+    pub fn hit_test(&self, x: f64, y: f64) -> Vec<String> {
+        let scene = self.scenes.get(&self.path).unwrap();
+        scene.hit_path(&Position::new(x, y)).iter().map(|item| item.get_name()).collect()
+    }
+
+    /// Whether the section or span called `name` is under the pointer
+    pub fn is_hovered(&self, name: &str) -> bool {
+        self.hovered.iter().any(|item| item.get_name() == name)
+    }
+
+    /// Press a button at (x, y), also focusing the innermost focusable span there unless prevented.
+    /// Pressing the primary button on a drag source arms a drag.
+    pub fn on_mouse_down(&mut self, x: f64, y: f64, button: MouseButton, modifiers: Modifiers) {
+        self.record(InputEvent::MouseDown { x, y, button, modifiers });
+        let ev = self.dispatch_event(Event::new(EventType::MouseDown, x, y).with_button(button).with_modifiers(modifiers));
+        // Like keys, buttons reach the global input state only when nothing stopped them
+        if !ev.consumed {
+            self.input.borrow_mut().on_mouse_down(button);
+        }
+        if !ev.default_prevented {
+            let mut path = self.scenes.get(&self.path).unwrap().hit_path(&Position::new(x, y));
+            match path.iter().rposition(|item| item.is_focusable()) {
+                Some(i) => {
+                    path.truncate(i + 1);
+                    self.set_focus(path);
+                },
+                None => self.blur(),
+            }
+            if button == MouseButton::Primary {
+                self.press_drag_source(x, y);
+                if self.drag.is_none() {
+                    self.scroll_drag = Some((Position::new(x, y), Position::new(x, y)));
+                    self.grab_scroll_at(x, y);
+                }
+            }
+        }
+    }
+
+    pub fn on_mouse_up(&mut self, x: f64, y: f64, button: MouseButton, modifiers: Modifiers) {
+        self.record(InputEvent::MouseUp { x, y, button, modifiers });
+        self.dispatch_event(Event::new(EventType::MouseUp, x, y).with_button(button).with_modifiers(modifiers));
+        // Released regardless, so no button stays held
+        self.input.borrow_mut().on_mouse_up(button);
+        if button == MouseButton::Primary {
+            self.finish_drag(x, y);
+            self.end_scroll_drag();
+        }
+    }
+
+    pub fn on_click(&mut self, x: f64, y: f64, button: MouseButton, modifiers: Modifiers) {
+        self.record(InputEvent::Click { x, y, button, modifiers });
+        self.dispatch_event(Event::new(EventType::Click, x, y).with_button(button).with_modifiers(modifiers));
+    }
+
+    pub fn on_double_click(&mut self, x: f64, y: f64, button: MouseButton, modifiers: Modifiers) {
+        self.record(InputEvent::DoubleClick { x, y, button, modifiers });
+        self.dispatch_event(Event::new(EventType::DoubleClick, x, y).with_button(button).with_modifiers(modifiers));
+    }
+
+    pub fn on_context_menu(&mut self, x: f64, y: f64, modifiers: Modifiers) {
+        self.record(InputEvent::ContextMenu { x, y, modifiers });
+        let ev = Event::new(EventType::ContextMenu, x, y).with_button(MouseButton::Secondary).with_modifiers(modifiers);
+        self.dispatch_event(ev);
+    }
+
+    /// Wheel scrolled by `delta_x`, `delta_y` pixels with the pointer at (x, y), scrolling
+    /// the content under it unless prevented
+    pub fn on_wheel(&mut self, x: f64, y: f64, delta_x: f64, delta_y: f64, modifiers: Modifiers) {
+        self.record(InputEvent::Wheel { x, y, delta_x, delta_y, modifiers });
+        let ev = self.dispatch_event(Event::new(EventType::Wheel, x, y).with_delta(delta_x, delta_y).with_modifiers(modifiers));
+        if !ev.default_prevented {
+            self.scroll_at(x, y, delta_x, delta_y);
+        }
+    }
+
+    /// The pointer entered the canvas at (x, y), entering the items under it
+    pub fn on_pointer_enter(&mut self, x: f64, y: f64) {
+        self.record(InputEvent::PointerEnter { x, y });
+        self.update_hover(x, y);
+    }
+
+    /// The pointer left the canvas, (x, y) being its last position over it
+    pub fn on_pointer_leave(&mut self, x: f64, y: f64) {
+        self.record(InputEvent::PointerLeave { x, y });
+        self.cancel_drag();
+        self.end_scroll_drag();
+        self.pointer = Position::new(x, y);
+        self.clear_hover();
+    }
+
+    /// A touch with pointer `id` came in contact at (x, y)
+    pub fn on_touch_start(&mut self, id: i32, x: f64, y: f64) {
+        self.record(InputEvent::TouchStart { id, x, y });
+        self.dispatch_event(Event::new(EventType::TouchStart, x, y).with_pointer_id(id));
+        if self.gestures.get_touches().is_empty() {
+            self.grab_scroll_at(x, y);
+        }
+        let gestures = self.gestures.touch_start(id, x, y, self.clock);
+        self.dispatch_gestures(gestures);
+    }
+
+    pub fn on_touch_move(&mut self, id: i32, x: f64, y: f64) {
+        self.record(InputEvent::TouchMove { id, x, y });
+        self.dispatch_event(Event::new(EventType::TouchMove, x, y).with_pointer_id(id));
+        let gestures = self.gestures.touch_move(id, x, y, self.clock);
+        self.dispatch_gestures(gestures);
+    }
+
+    pub fn on_touch_end(&mut self, id: i32, x: f64, y: f64) {
+        self.record(InputEvent::TouchEnd { id, x, y });
+        self.dispatch_event(Event::new(EventType::TouchEnd, x, y).with_pointer_id(id));
+        let origin = self.touch_origin();
+        let gestures = self.gestures.touch_end(id, x, y, self.clock);
+        self.dispatch_gestures(gestures);
+        self.release_touch_scroll(origin);
+    }
+
+    /// The platform interrupted the touch, it ends without completing any gesture
+    pub fn on_touch_cancel(&mut self, id: i32) {
+        self.record(InputEvent::TouchCancel { id });
+        if let Some(touch) = self.gestures.get_touch(id) {
+            let (x, y) = (touch.pos.x(), touch.pos.y());
+            self.dispatch_event(Event::new(EventType::TouchCancel, x, y).with_pointer_id(id));
+        }
+        let origin = self.touch_origin();
+        let gestures = self.gestures.touch_cancel(id);
+        self.dispatch_gestures(gestures);
+        self.release_touch_scroll(origin);
+    }
+
+    // Where the first touch in contact started, the content it pans scrolls from there
+    fn touch_origin(&self) -> Option<Position> {
+        self.gestures.get_touches().first().map(|touch| touch.start)
+    }
+
+    fn release_touch_scroll(&mut self, origin: Option<Position>) {
+        if let (true, Some(origin)) = (self.gestures.get_touches().is_empty(), origin) {
+            self.release_scroll_at(origin.x(), origin.y());
+        }
+    }
+
+    /// Touches in contact, in the order they started
+    pub fn get_touches(&self) -> &[Touch] {
+        self.gestures.get_touches()
+    }
+
+    // Deliver the gestures to the scene, pans scrolling the content under them unless prevented
+    fn dispatch_gestures(&mut self, gestures: Vec<Gesture>) {
+        for gesture in gestures {
+            let ev = self.dispatch_event(gesture.to_event());
+            if let Gesture::Pan { x, y, dx, dy } = gesture {
+                if !ev.default_prevented {
+                    let origin = self.touch_origin().unwrap_or(Position::new(x - dx, y - dy));
+                    self.scroll_path(origin.x(), origin.y(), -dx, -dy, true);
+                }
+            }
+        }
     }
 
     pub fn on_keydown(&self, key: &str) {
-        self.input.borrow_mut().on_keydown(key);
+        self.on_keydown_with(key, Modifiers::default());
     }
 
     pub fn on_keyup(&self, key: &str) {
-        self.input.borrow_mut().on_keyup(key);
+        self.on_keyup_with(key, Modifiers::default());
+    }
+
+    /// Deliver a key press to the focused span and its sections, the global input state
+    /// only sees it when nothing stopped its propagation. Tab moves the focus unless prevented.
+    ///
+    /// Returns the dispatched event, with its `consumed` and `default_prevented` flags updated.
+    pub fn on_keydown_with(&self, key: &str, modifiers: Modifiers) -> Event {
+        self.record(InputEvent::KeyDown { key: key.to_string(), modifiers });
+        let ev = self.dispatch_key(Event::new_key(EventType::KeyDown, key).with_modifiers(modifiers));
+        if !ev.consumed {
+            self.input.borrow_mut().on_keydown(key);
+        }
+        if key == "Tab" && !ev.default_prevented {
+            self.move_focus(modifiers.shift);
+        }
+        ev
+    }
+
+    /// Deliver a key release like `on_keydown_with`, returns the dispatched event
+    pub fn on_keyup_with(&self, key: &str, modifiers: Modifiers) -> Event {
+        self.record(InputEvent::KeyUp { key: key.to_string(), modifiers });
+        let ev = self.dispatch_key(Event::new_key(EventType::KeyUp, key).with_modifiers(modifiers));
+        if !ev.consumed {
+            self.input.borrow_mut().on_keyup(key);
+        }
+        ev
+    }
+
+    fn dispatch_key(&self, mut ev: Event) -> Event {
+        // Listeners may move the focus, deliver along the path it had when the key came
+        let focused = self.focused.borrow().clone();
+        if let Some((target, sections)) = focused.split_last() {
+            target.deliver_event(&mut ev, EventPhase::Target, &self.state);
+            for section in sections.iter().rev() {
+                if ev.consumed {
+                    break;
+                }
+                section.deliver_event(&mut ev, EventPhase::Bubble, &self.state);
+            }
+        }
+        ev.phase = EventPhase::None;
+        ev
+    }
+
+    /// Focus the focusable span called `name`, returns false if the active scene has none
+    pub fn focus(&mut self, name: &str) -> bool {
+        let paths = self.scenes.get(&self.path).unwrap().focusable_paths();
+        match paths.into_iter().find(|path| path.last().map(|span| span.get_name() == name).unwrap_or(false)) {
+            Some(path) => {
+                self.set_focus(path);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn blur(&mut self) {
+        self.set_focus(Vec::new());
+    }
+
+    /// Name of the focused span
+    pub fn get_focus(&self) -> Option<String> {
+        self.focused.borrow().last().map(|span| span.get_name())
+    }
+
+    fn set_focus(&self, path: Vec<Content>) {
+        let unchanged = match (self.focused.borrow().last(), path.last()) {
+            (Some(a), Some(b)) => a.ptr_eq(b),
+            (None, None) => true,
+            _ => false,
+        };
+        let focused = path.last().cloned();
+        let old = self.focused.replace(path);
+        if unchanged {
+            return;
+        }
+        if let Some(span) = old.last() {
+            span.deliver_event(&mut Event::new(EventType::Blur, 0., 0.), EventPhase::Target, &self.state);
+        }
+        if let Some(span) = focused {
+            span.deliver_event(&mut Event::new(EventType::Focus, 0., 0.), EventPhase::Target, &self.state);
+        }
+    }
+
+    // Focus the next focusable span in tab order, or the previous one, wrapping around
+    fn move_focus(&self, backwards: bool) {
+        let mut paths = self.scenes.get(&self.path).unwrap().focusable_paths();
+        if paths.is_empty() {
+            return;
+        }
+        let count = paths.len();
+        let current = self.focused.borrow().last()
+            .and_then(|span| paths.iter().position(|path| path.last().unwrap().ptr_eq(span)));
+        let next = match (current, backwards) {
+            (Some(i), false) => (i + 1) % count,
+            (Some(i), true) => (i + count - 1) % count,
+            (None, false) => 0,
+            (None, true) => count - 1,
+        };
+        self.set_focus(paths.swap_remove(next));
     }
 
     pub fn new_section(&self, name: &str, width: f32, height: f32, padding: f32) -> SectionRef {
@@ -245,6 +1656,10 @@ impl Application {
         self.state.clone()
     }
 
+    pub fn get_meta(&self) -> &CanvasMeta {
+        &self.meta
+    }
+
     pub fn get_fps(&self) -> u32 {
         self.counter.borrow().get()
     }
@@ -0,0 +1,95 @@
+//! Dragging spans with the pointer and dropping them on sections or spans accepting them.
+//!
+//! A span becomes a drag source by returning a payload from `SpanTrait::drag_payload`.
+//! Once pressed and moved past `DRAG_THRESHOLD`, a translucent ghost of it follows the
+//! pointer, and the innermost item under the pointer accepting the payload, through
+//! `SpanTrait::accepts_drop` or `Section::set_drop_target`, becomes the drop target.
+
+use std::any::Any;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::component::{Position, Rect};
+use crate::content::Content;
+
+/// Distance in logical pixels the pointer has to travel while pressed before a drag starts
+pub const DRAG_THRESHOLD: f64 = 4.;
+/// Opacity of the ghost following the pointer
+pub const GHOST_ALPHA: f64 = 0.5;
+// Size of the ghost of sources without a known area
+const GHOST_SIZE: f64 = 24.;
+
+/// Data carried by a drag, tagged with a kind drop targets can filter on
+#[derive(Clone)]
+pub struct DragPayload {
+    kind: String,
+    data: Rc<dyn Any>,
+}
+
+impl DragPayload {
+    pub fn new<T: 'static>(kind: &str, data: T) -> Self {
+        Self { kind: kind.to_string(), data: Rc::new(data) }
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// The carried data, `None` if it is not a `T`
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.data.downcast_ref::<T>()
+    }
+
+    pub fn is<T: 'static>(&self) -> bool {
+        self.data.is::<T>()
+    }
+}
+
+impl fmt::Debug for DragPayload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DragPayload").field("kind", &self.kind).finish()
+    }
+}
+
+/// Predicate of a drop target, true for the payloads it accepts
+pub type DropPredicate = Rc<dyn Fn(&DragPayload) -> bool>;
+
+// A press on a drag source, which turns into a drag once the pointer moved far enough
+pub(crate) struct DragState {
+    pub source: Content,
+    pub payload: DragPayload,
+    pub origin: Position,
+    pub pos: Position,
+    // Area of the source on the canvas when pressed, and the scroll offset it is drawn moved by
+    pub rect: Option<Rect>,
+    pub scroll: (f64, f64),
+    pub active: bool,
+    pub target: Option<Content>,
+}
+
+impl DragState {
+    pub fn new(source: Content, payload: DragPayload, origin: Position, scroll: (f64, f64)) -> Self {
+        let rect = source.get_rect().map(|rect| Rect::new(rect.x - scroll.0, rect.y - scroll.1, rect.w, rect.h));
+        Self { source, payload, origin, pos: origin, rect, scroll, active: false, target: None }
+    }
+
+    /// Whether the pointer travelled far enough from the press for the drag to start
+    pub fn past_threshold(&self) -> bool {
+        let (dx, dy) = (self.pos.x() - self.origin.x(), self.pos.y() - self.origin.y());
+        dx * dx + dy * dy >= DRAG_THRESHOLD * DRAG_THRESHOLD
+    }
+
+    /// Offset of the ghost from the source
+    pub fn offset(&self) -> (f64, f64) {
+        (self.pos.x() - self.origin.x(), self.pos.y() - self.origin.y())
+    }
+
+    /// Area covered by the ghost
+    pub fn ghost_rect(&self) -> Rect {
+        let (dx, dy) = self.offset();
+        match self.rect {
+            Some(rect) => Rect::new(rect.x + dx, rect.y + dy, rect.w, rect.h),
+            None => Rect::new(self.pos.x() - GHOST_SIZE / 2., self.pos.y() - GHOST_SIZE / 2., GHOST_SIZE, GHOST_SIZE),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Gestures recognized from the touches tracked by `Application`.
+//!
+//! Times are in milliseconds of application time, advanced by `Application::tick_with`,
+//! so recorded sessions recognize the same gestures when replayed.
+
+use std::f64::consts::PI;
+
+use crate::component::{Event, EventType, Position};
+
+/// Distance in logical pixels a touch may travel and still count as a tap or long press
+pub const TAP_SLOP: f64 = 10.;
+/// Longest press counting as a tap
+pub const TAP_TIMEOUT: f64 = 300.;
+/// Time a touch has to rest before it counts as a long press
+pub const LONG_PRESS_DELAY: f64 = 500.;
+/// Slowest release in pixels per millisecond ending a pan with a swipe
+pub const SWIPE_MIN_VELOCITY: f64 = 0.3;
+/// Shortest pan ending with a swipe
+pub const SWIPE_MIN_DISTANCE: f64 = 30.;
+// Time a touch may rest before its release, for its last velocity to still count
+const SWIPE_MAX_REST: f64 = 100.;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Gesture {
+    /// A short press released without moving
+    Tap { x: f64, y: f64 },
+    /// A touch resting in place for `LONG_PRESS_DELAY`
+    LongPress { x: f64, y: f64 },
+    /// A single touch moved by `dx`, `dy` since the previous pan
+    Pan { x: f64, y: f64, dx: f64, dy: f64 },
+    /// The first two touches moved apart by `scale` since the previous pinch, around (x, y)
+    Pinch { x: f64, y: f64, scale: f64 },
+    /// The first two touches turned by `angle` radians since the previous rotation, around (x, y)
+    Rotate { x: f64, y: f64, angle: f64 },
+    /// A pan released fast enough, having moved by `dx`, `dy` overall
+    Swipe { x: f64, y: f64, dx: f64, dy: f64 },
+}
+
+impl Gesture {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Gesture::Tap { .. } => EventType::Tap,
+            Gesture::LongPress { .. } => EventType::LongPress,
+            Gesture::Pan { .. } => EventType::Pan,
+            Gesture::Pinch { .. } => EventType::Pinch,
+            Gesture::Rotate { .. } => EventType::Rotate,
+            Gesture::Swipe { .. } => EventType::Swipe,
+        }
+    }
+
+    /// Event carrying the gesture, positioned where it happened
+    pub fn to_event(&self) -> Event {
+        match *self {
+            Gesture::Tap { x, y } | Gesture::LongPress { x, y } => Event::new(self.event_type(), x, y),
+            Gesture::Pan { x, y, dx, dy } | Gesture::Swipe { x, y, dx, dy } =>
+                Event::new(self.event_type(), x, y).with_delta(dx, dy),
+            Gesture::Pinch { x, y, scale } => {
+                let mut ev = Event::new(self.event_type(), x, y);
+                ev.scale = scale;
+                ev
+            },
+            Gesture::Rotate { x, y, angle } => {
+                let mut ev = Event::new(self.event_type(), x, y);
+                ev.rotation = angle;
+                ev
+            },
+        }
+    }
+}
+
+/// A touch in contact, by the pointer id the platform gave it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Touch {
+    pub id: i32,
+    pub start: Position,
+    pub pos: Position,
+    pub start_time: f64,
+    // Time and velocity of the last move, in pixels per millisecond
+    time: f64,
+    velocity: (f64, f64),
+}
+
+impl Touch {
+    fn distance(&self) -> f64 {
+        (self.pos.x() - self.start.x()).hypot(self.pos.y() - self.start.y())
+    }
+}
+
+/// Turns the touches in contact into gestures.
+///
+/// A sequence runs from the first touch down to the last one up. Sequences with a single
+/// touch produce taps, long presses, pans and swipes, the ones involving more touches
+/// pinches and rotations of the first two.
+#[derive(Default)]
+pub struct GestureRecognizer {
+    touches: Vec<Touch>,
+    panning: bool,
+    long_pressed: bool,
+    multi: bool,
+    // Distance and angle between the first two touches
+    span: Option<(f64, f64)>,
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_touches(&self) -> &[Touch] {
+        &self.touches
+    }
+
+    pub fn get_touch(&self, id: i32) -> Option<&Touch> {
+        self.touches.iter().find(|touch| touch.id == id)
+    }
+
+    pub fn touch_start(&mut self, id: i32, x: f64, y: f64, time: f64) -> Vec<Gesture> {
+        // A touch starting twice lost its end, start over
+        self.touches.retain(|touch| touch.id != id);
+        let pos = Position::new(x, y);
+        self.touches.push(Touch { id, start: pos, pos, start_time: time, time, velocity: (0., 0.) });
+        if self.touches.len() == 1 {
+            self.panning = false;
+            self.long_pressed = false;
+            self.multi = false;
+        } else {
+            self.multi = true;
+            self.panning = false;
+        }
+        self.span = self.measure_span();
+        Vec::new()
+    }
+
+    pub fn touch_move(&mut self, id: i32, x: f64, y: f64, time: f64) -> Vec<Gesture> {
+        let touch = match self.touches.iter_mut().find(|touch| touch.id == id) {
+            Some(touch) => touch,
+            None => return Vec::new(),
+        };
+        let (dx, dy) = (x - touch.pos.x(), y - touch.pos.y());
+        let dt = time - touch.time;
+        if dt > 0. {
+            touch.velocity = (dx / dt, dy / dt);
+        }
+        touch.pos = Position::new(x, y);
+        touch.time = time;
+        let touch = *touch;
+
+        let mut gestures = Vec::new();
+        if self.multi {
+            if let (Some((distance, angle)), Some(span)) = (self.span, self.measure_span()) {
+                let (cx, cy) = self.centroid();
+                if distance > 0. && span.0 != distance {
+                    gestures.push(Gesture::Pinch { x: cx, y: cy, scale: span.0 / distance });
+                }
+                let turn = normalize_angle(span.1 - angle);
+                if turn != 0. {
+                    gestures.push(Gesture::Rotate { x: cx, y: cy, angle: turn });
+                }
+                self.span = Some(span);
+            }
+        } else if self.panning {
+            gestures.push(Gesture::Pan { x, y, dx, dy });
+        } else if touch.distance() > TAP_SLOP {
+            // Catch up with the movement within the slop
+            self.panning = true;
+            gestures.push(Gesture::Pan { x, y, dx: x - touch.start.x(), dy: y - touch.start.y() });
+        }
+        gestures
+    }
+
+    pub fn touch_end(&mut self, id: i32, x: f64, y: f64, time: f64) -> Vec<Gesture> {
+        let index = match self.touches.iter().position(|touch| touch.id == id) {
+            Some(index) => index,
+            None => return Vec::new(),
+        };
+        let touch = self.touches.remove(index);
+        self.span = self.measure_span();
+        let mut gestures = Vec::new();
+        if !self.touches.is_empty() || self.multi {
+            return gestures;
+        }
+        if self.panning {
+            let (vx, vy) = touch.velocity;
+            let (dx, dy) = (x - touch.start.x(), y - touch.start.y());
+            if time - touch.time <= SWIPE_MAX_REST
+                && vx.hypot(vy) >= SWIPE_MIN_VELOCITY
+                && dx.hypot(dy) >= SWIPE_MIN_DISTANCE {
+                gestures.push(Gesture::Swipe { x, y, dx, dy });
+            }
+        } else if !self.long_pressed && time - touch.start_time <= TAP_TIMEOUT {
+            gestures.push(Gesture::Tap { x, y });
+        }
+        gestures
+    }
+
+    /// The touch was interrupted by the platform, it ends without any gesture
+    pub fn touch_cancel(&mut self, id: i32) -> Vec<Gesture> {
+        self.touches.retain(|touch| touch.id != id);
+        self.span = self.measure_span();
+        if self.touches.is_empty() {
+            // Nothing may complete the sequence anymore
+            self.multi = true;
+        }
+        Vec::new()
+    }
+
+    /// Recognize the gestures depending on time alone, i.e. long presses
+    pub fn tick(&mut self, time: f64) -> Vec<Gesture> {
+        match self.touches.as_slice() {
+            [touch] if !self.multi && !self.panning && !self.long_pressed
+                && time - touch.start_time >= LONG_PRESS_DELAY => {
+                self.long_pressed = true;
+                vec![Gesture::LongPress { x: touch.pos.x(), y: touch.pos.y() }]
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    fn measure_span(&self) -> Option<(f64, f64)> {
+        match self.touches.as_slice() {
+            [a, b, ..] => {
+                let (dx, dy) = (b.pos.x() - a.pos.x(), b.pos.y() - a.pos.y());
+                Some((dx.hypot(dy), dy.atan2(dx)))
+            },
+            _ => None,
+        }
+    }
+
+    fn centroid(&self) -> (f64, f64) {
+        let count = self.touches.len().max(1) as f64;
+        let x = self.touches.iter().map(|touch| touch.pos.x()).sum::<f64>() / count;
+        let y = self.touches.iter().map(|touch| touch.pos.y()).sum::<f64>() / count;
+        (x, y)
+    }
+}
+
+// Wrap an angle into (-PI, PI]
+fn normalize_angle(angle: f64) -> f64 {
+    let mut angle = angle % (2. * PI);
+    if angle > PI {
+        angle -= 2. * PI;
+    } else if angle <= -PI {
+        angle += 2. * PI;
+    }
+    angle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_touch_gestures() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.touch_start(1, 10., 10., 0.);
+        assert_eq!(recognizer.touch_end(1, 12., 10., 100.), vec![Gesture::Tap { x: 12., y: 10. }]);
+
+        recognizer.touch_start(1, 10., 10., 1000.);
+        assert!(recognizer.tick(1400.).is_empty());
+        assert_eq!(recognizer.tick(1500.), vec![Gesture::LongPress { x: 10., y: 10. }]);
+        assert!(recognizer.tick(1600.).is_empty());
+        assert!(recognizer.touch_end(1, 10., 10., 1700.).is_empty());
+
+        recognizer.touch_start(2, 0., 0., 2000.);
+        assert!(recognizer.touch_move(2, 5., 0., 2010.).is_empty());
+        assert_eq!(recognizer.touch_move(2, 20., 0., 2020.), vec![Gesture::Pan { x: 20., y: 0., dx: 20., dy: 0. }]);
+        assert_eq!(recognizer.touch_move(2, 60., 0., 2030.), vec![Gesture::Pan { x: 60., y: 0., dx: 40., dy: 0. }]);
+        assert_eq!(recognizer.touch_end(2, 60., 0., 2040.), vec![Gesture::Swipe { x: 60., y: 0., dx: 60., dy: 0. }]);
+
+        // A pan coming to rest before its release is no swipe
+        recognizer.touch_start(3, 0., 0., 3000.);
+        recognizer.touch_move(3, 60., 0., 3020.);
+        assert!(recognizer.touch_end(3, 60., 0., 3500.).is_empty());
+    }
+
+    #[test]
+    fn test_two_touch_gestures() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.touch_start(1, 0., 0., 0.);
+        recognizer.touch_start(2, 10., 0., 10.);
+        assert_eq!(recognizer.get_touches().len(), 2);
+        assert_eq!(recognizer.touch_move(2, 20., 0., 20.), vec![Gesture::Pinch { x: 10., y: 0., scale: 2. }]);
+
+        let gestures = recognizer.touch_move(2, 0., 20., 30.);
+        assert_eq!(gestures.len(), 1);
+        match gestures[0] {
+            Gesture::Rotate { angle, .. } => assert!((angle - PI / 2.).abs() < 1e-9),
+            ref gesture => panic!("unexpected {:?}", gesture),
+        }
+        // Lifting the fingers of a pinch is no tap
+        assert!(recognizer.touch_end(1, 0., 0., 40.).is_empty());
+        assert!(recognizer.touch_end(2, 0., 20., 50.).is_empty());
+        assert!(recognizer.get_touch(2).is_none());
+    }
+}
@@ -1,69 +1,450 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 use std::rc::Rc;
 use std::cell::RefCell;
 
+use crate::component::MouseButton;
+use crate::utils::unquote;
+
 pub type Input = Rc<RefCell<InputProto>>;
 
+/// A key, as the DOM `KeyboardEvent.key`, or a mouse button an action can be bound to
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Binding {
+    Key(String),
+    Button(MouseButton),
+}
+
+impl Binding {
+    pub fn key(key: &str) -> Self {
+        Binding::Key(key.to_string())
+    }
+}
+
+impl From<&str> for Binding {
+    fn from(key: &str) -> Self {
+        Binding::key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Binding::Button(button)
+    }
+}
+
+impl From<&Binding> for Binding {
+    fn from(binding: &Binding) -> Self {
+        binding.clone()
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key {:?}", key),
+            Binding::Button(button) => write!(f, "button {}", button.index()),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.starts_with("key ") {
+            Ok(Binding::Key(unquote(&value[4..])?))
+        } else if value.starts_with("button ") {
+            value[7..].trim().parse::<i16>().ok()
+                .and_then(MouseButton::from_index)
+                .map(Binding::Button)
+                .ok_or_else(|| format!("Invalid button in {:?}", value))
+        } else {
+            Err(format!("Unknown binding {:?}", value))
+        }
+    }
+}
+
+/// Named actions and axes bound to keys and buttons, so code polls `jump` or `move_x`
+/// rather than key names, and players can rebind them.
+///
+/// Bindings serialize to one line each, e.g.
+///
+/// ```text
+/// action jump key " "
+/// action jump button 0
+/// axis move_x + key "ArrowRight"
+/// axis move_x - key "ArrowLeft"
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ActionMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    // Bindings pushing each axis towards 1 and -1
+    axes: BTreeMap<String, (Vec<Binding>, Vec<Binding>)>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a binding triggering the action, keeping the existing ones.
+    /// Names of actions and axes may not be empty or contain whitespace.
+    pub fn bind_action(&mut self, name: &str, binding: Binding) -> Result<(), String> {
+        Self::check_name(name)?;
+        let bindings = self.actions.entry(name.to_string()).or_insert_with(Vec::new);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    /// Replace the bindings of the action
+    pub fn rebind_action(&mut self, name: &str, bindings: Vec<Binding>) -> Result<(), String> {
+        Self::check_name(name)?;
+        self.actions.insert(name.to_string(), bindings);
+        Ok(())
+    }
+
+    /// Remove a binding from the action, returns false if it was not bound to it
+    pub fn unbind_action(&mut self, name: &str, binding: &Binding) -> bool {
+        match self.actions.get_mut(name) {
+            Some(bindings) => {
+                let count = bindings.len();
+                bindings.retain(|b| b != binding);
+                bindings.len() != count
+            },
+            None => false,
+        }
+    }
+
+    pub fn get_action(&self, name: &str) -> &[Binding] {
+        self.actions.get(name).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    /// Add bindings pushing the axis towards 1 and -1, keeping the existing ones
+    pub fn bind_axis(&mut self, name: &str, positive: Binding, negative: Binding) -> Result<(), String> {
+        Self::check_name(name)?;
+        let (pos, neg) = self.axes.entry(name.to_string()).or_insert_with(Default::default);
+        if !pos.contains(&positive) {
+            pos.push(positive);
+        }
+        if !neg.contains(&negative) {
+            neg.push(negative);
+        }
+        Ok(())
+    }
+
+    /// Replace the bindings of the axis
+    pub fn rebind_axis(&mut self, name: &str, positive: Vec<Binding>, negative: Vec<Binding>) -> Result<(), String> {
+        Self::check_name(name)?;
+        self.axes.insert(name.to_string(), (positive, negative));
+        Ok(())
+    }
+
+    // Names are written unquoted and end at the first space when parsed
+    fn check_name(name: &str) -> Result<(), String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid action or axis name {:?}", name));
+        }
+        Ok(())
+    }
+
+    /// Bindings pushing the axis towards 1 and -1
+    pub fn get_axis(&self, name: &str) -> (&[Binding], &[Binding]) {
+        match self.axes.get(name) {
+            Some((pos, neg)) => (pos.as_slice(), neg.as_slice()),
+            None => (&[], &[]),
+        }
+    }
+
+    /// Forget the action or axis called `name`, returns false if there was none
+    pub fn remove(&mut self, name: &str) -> bool {
+        let action = self.actions.remove(name).is_some();
+        let axis = self.axes.remove(name).is_some();
+        action || axis
+    }
+
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let mut map = Self::new();
+        for line in data.lines().map(|line| line.trim()).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let mut parts = line.splitn(3, ' ');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("action"), Some(name), Some(binding)) => map.bind_action(name, binding.parse()?)?,
+                (Some("axis"), Some(name), Some(rest)) => {
+                    Self::check_name(name)?;
+                    let (pos, neg) = map.axes.entry(name.to_string()).or_insert_with(Default::default);
+                    if rest.starts_with("+ ") {
+                        pos.push(rest[2..].parse()?);
+                    } else if rest.starts_with("- ") {
+                        neg.push(rest[2..].parse()?);
+                    } else {
+                        return Err(format!("Expected + or - in {:?}", line));
+                    }
+                },
+                _ => return Err(format!("Invalid binding {:?}", line)),
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl fmt::Display for ActionMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, bindings) in self.actions.iter() {
+            for binding in bindings.iter() {
+                writeln!(f, "action {} {}", name, binding)?;
+            }
+        }
+        for (name, (pos, neg)) in self.axes.iter() {
+            for binding in pos.iter() {
+                writeln!(f, "axis {} + {}", name, binding)?;
+            }
+            for binding in neg.iter() {
+                writeln!(f, "axis {} - {}", name, binding)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// State of the keys and buttons as of the last frame.
+///
+/// Presses and releases received between two frames are applied together by `advance`,
+/// which `Application::tick` calls once per tick, so every span ticked in a frame sees
+/// the same state and a press is `just_pressed` for exactly one frame.
 pub struct InputProto {
-    keydown: HashSet<String>,
-    keydown_cache: HashSet<String>,
-    keyup: HashSet<String>,
-    // axis: HashMap<String, f32>
+    // Bindings held as of the last frame, with how long they have been held in milliseconds
+    held: HashMap<Binding, f64>,
+    // Edges within the last frame
+    pressed: HashSet<Binding>,
+    released: HashSet<Binding>,
+    // Presses (true) and releases received since the last frame, in order
+    pending: Vec<(Binding, bool)>,
+    actions: ActionMap,
 }
 
 impl InputProto {
     pub fn new() -> Input {
         Rc::new(RefCell::new(Self {
-            keydown: HashSet::new(),
-            keydown_cache: HashSet::new(),
-            keyup: HashSet::new(),
-            // axis: HashMap::new(),
+            held: HashMap::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            pending: Vec::new(),
+            actions: ActionMap::new(),
         }))
     }
 
-    /*
-    pub fn axis(&self, key: &str) -> f32 {
-        match self.axis.get(key) {
-            Some(v) => v.clone(),
-            None => 0.,
+    /// Start a new frame `dt` milliseconds after the previous one, applying the presses
+    /// and releases received in between
+    pub fn advance(&mut self, dt: f64) {
+        self.pressed.clear();
+        self.released.clear();
+        for duration in self.held.values_mut() {
+            *duration += dt;
+        }
+        for (binding, down) in self.pending.drain(..) {
+            if down {
+                // Repeated presses of a held key are not new presses
+                if !self.held.contains_key(&binding) {
+                    self.held.insert(binding.clone(), 0.);
+                    self.pressed.insert(binding);
+                }
+            } else if self.held.remove(&binding).is_some() {
+                self.released.insert(binding);
+            }
         }
     }
-    */
 
-    pub fn axis(&mut self, pos: &str, neg: &str) -> f32 {
-        if self.keydown(pos) {
-            if self.keydown(neg) {
-                0.
-            } else {
-                1.
-            }
-        } else if self.keydown(neg) {
-            -1.
-        } else {
-            0.
+    /// Whether the key or button was held during the last frame, including presses
+    /// released before it ended
+    pub fn is_down<B: Into<Binding>>(&self, binding: B) -> bool {
+        let binding = binding.into();
+        self.held.contains_key(&binding) || self.pressed.contains(&binding)
+    }
+
+    /// Whether the key or button was pressed during the last frame
+    pub fn just_pressed<B: Into<Binding>>(&self, binding: B) -> bool {
+        self.pressed.contains(&binding.into())
+    }
+
+    /// Whether the key or button was released during the last frame
+    pub fn just_released<B: Into<Binding>>(&self, binding: B) -> bool {
+        self.released.contains(&binding.into())
+    }
+
+    /// Milliseconds the key or button has been held for as of the last frame, 0 if not held
+    pub fn hold_duration<B: Into<Binding>>(&self, binding: B) -> f64 {
+        self.held.get(&binding.into()).cloned().unwrap_or(0.)
+    }
+
+    pub fn get_actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    /// Bindings to rebind at runtime
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    pub fn set_actions(&mut self, actions: ActionMap) {
+        self.actions = actions;
+    }
+
+    /// Whether any binding of the action was held during the last frame
+    pub fn action(&self, name: &str) -> bool {
+        self.actions.get_action(name).iter().any(|binding| self.is_down(binding))
+    }
+
+    /// Whether a binding of the action was pressed during the last frame
+    pub fn action_just_pressed(&self, name: &str) -> bool {
+        self.actions.get_action(name).iter().any(|binding| self.just_pressed(binding))
+    }
+
+    /// Whether the action stopped during the last frame, no binding of it being held anymore
+    pub fn action_just_released(&self, name: &str) -> bool {
+        let bindings = self.actions.get_action(name);
+        bindings.iter().any(|binding| self.just_released(binding))
+            && !bindings.iter().any(|binding| self.held.contains_key(binding))
+    }
+
+    /// Milliseconds the action has been held for, by its longest held binding
+    pub fn action_hold_duration(&self, name: &str) -> f64 {
+        self.actions.get_action(name).iter().map(|binding| self.hold_duration(binding)).fold(0., f64::max)
+    }
+
+    /// Value of the axis, 1 or -1 when only its positive or negative bindings are held
+    pub fn action_axis(&self, name: &str) -> f32 {
+        let (pos, neg) = self.actions.get_axis(name);
+        let pos = pos.iter().any(|binding| self.is_down(binding));
+        let neg = neg.iter().any(|binding| self.is_down(binding));
+        match (pos, neg) {
+            (true, false) => 1.,
+            (false, true) => -1.,
+            _ => 0.,
         }
     }
 
-    pub fn keydown(&mut self, key: &str) -> bool {
-        self.keydown.contains(key) || self.keydown_cache.take(key).is_some()
+    pub fn axis(&self, pos: &str, neg: &str) -> f32 {
+        match (self.is_down(pos), self.is_down(neg)) {
+            (true, false) => 1.,
+            (false, true) => -1.,
+            _ => 0.,
+        }
+    }
+
+    /// Deprecated, use `is_down`
+    pub fn keydown(&self, key: &str) -> bool {
+        self.is_down(key)
     }
 
+    /// Deprecated, use `just_released`
     pub fn keyup(&self, key: &str) -> bool {
-        self.keyup.contains(key)
+        self.just_released(key)
     }
 
     pub fn on_keydown(&mut self, key: &str) {
         // log!("Keydown {}", key);
-        self.keydown.insert(key.to_string());
+        self.pending.push((Binding::key(key), true));
     }
 
     pub fn on_keyup(&mut self, key: &str) {
         // log!("Keyup {}", key);
-        match self.keydown.take(key) {
-            Some(v) => { self.keydown_cache.insert(v.clone()); },
-            None => {},
-        }
+        self.pending.push((Binding::key(key), false));
+    }
+
+    pub fn on_mouse_down(&mut self, button: MouseButton) {
+        self.pending.push((Binding::Button(button), true));
+    }
+
+    pub fn on_mouse_up(&mut self, button: MouseButton) {
+        self.pending.push((Binding::Button(button), false));
     }
 
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_map_round_trip() {
+        let mut map = ActionMap::new();
+        map.bind_action("jump", Binding::key(" ")).unwrap();
+        map.bind_action("jump", Binding::Button(MouseButton::Primary)).unwrap();
+        map.bind_axis("move_x", Binding::key("ArrowRight"), Binding::key("ArrowLeft")).unwrap();
+        map.bind_axis("move_x", Binding::key("d"), Binding::key("a")).unwrap();
+        let text = map.to_string();
+        assert!(text.contains("action jump key \" \"\n"));
+        assert!(text.contains("axis move_x - key \"a\"\n"));
+        assert_eq!(ActionMap::parse(&text).unwrap(), map);
+
+        // Names with whitespace would not parse back
+        assert!(map.bind_action("double jump", Binding::key("j")).is_err());
+        assert!(map.bind_axis("move\tx", Binding::key("d"), Binding::key("a")).is_err());
+        assert!(map.rebind_action("", vec![Binding::key("j")]).is_err());
+        assert!(map.rebind_axis("move y", vec![], vec![]).is_err());
+        assert_eq!(ActionMap::parse(&map.to_string()).unwrap(), map);
+        assert!(ActionMap::parse("action jump pedal 1").is_err());
+        assert!(ActionMap::parse("axis move_x key \"d\"").is_err());
+    }
+
+    #[test]
+    fn test_actions_follow_bindings() {
+        let input = InputProto::new();
+        let mut input = input.borrow_mut();
+        input.set_actions(ActionMap::parse("action fire button 0\naxis move_x + key \"d\"\naxis move_x - key \"a\"").unwrap());
+        input.on_keydown("a");
+        input.advance(16.);
+        assert_eq!(input.action_axis("move_x"), -1.);
+        input.on_keydown("d");
+        input.advance(16.);
+        assert_eq!(input.action_axis("move_x"), 0.);
+
+        // A click within a frame fires for that frame only
+        assert!(!input.action("fire"));
+        input.on_mouse_down(MouseButton::Primary);
+        input.on_mouse_up(MouseButton::Primary);
+        input.advance(16.);
+        assert!(input.action("fire") && input.action_just_pressed("fire") && input.action_just_released("fire"));
+        input.advance(16.);
+        assert!(!input.action("fire"));
+
+        input.actions_mut().rebind_action("fire", vec![Binding::key("f")]).unwrap();
+        input.on_keydown("f");
+        input.advance(16.);
+        assert!(input.action("fire"));
+        assert!(!input.action("missing"));
+    }
+
+    #[test]
+    fn test_edges_last_one_frame() {
+        let input = InputProto::new();
+        let mut input = input.borrow_mut();
+        input.on_keydown("a");
+        assert!(!input.is_down("a"));
+        input.advance(16.);
+        assert!(input.is_down("a") && input.just_pressed("a"));
+        assert_eq!(input.hold_duration("a"), 0.);
+
+        // Repeats of a held key are no presses
+        input.on_keydown("a");
+        input.advance(16.);
+        assert!(input.is_down("a") && !input.just_pressed("a"));
+        assert_eq!(input.hold_duration("a"), 16.);
+
+        input.on_keyup("a");
+        input.advance(16.);
+        assert!(!input.is_down("a") && input.just_released("a"));
+        assert_eq!(input.hold_duration("a"), 0.);
+        input.advance(16.);
+        assert!(!input.just_released("a"));
+
+        // Releasing what was never pressed is no release
+        input.on_mouse_up(MouseButton::Secondary);
+        input.advance(16.);
+        assert!(!input.just_released(MouseButton::Secondary));
+    }
+}
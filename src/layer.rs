@@ -0,0 +1,66 @@
+//! Ordered layers of a scene, each drawn to its own surface and stacked in declaration order.
+
+use std::rc::Rc;
+
+use crate::canvas::Canvas2D;
+use crate::prelude::renderer::{ContextGL, HtmlCanvasElement};
+
+/// Layer the scene chrome and every span render to unless they pick another one
+pub const DEFAULT_LAYER: &str = "ui";
+/// Layer backed by the WebGL canvas passed to `Application::new_with_canvas_id`
+pub const WORLD_LAYER: &str = "world";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LayerKind {
+    /// Drawn through `Canvas2D`
+    Canvas,
+    /// Drawn through the WebGL context
+    WebGL,
+}
+
+/// A layer declared by a scene, composited over the ones declared before it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Layer {
+    pub name: String,
+    pub kind: LayerKind,
+}
+
+impl Layer {
+    pub fn new(name: &str, kind: LayerKind) -> Self {
+        Self { name: name.to_string(), kind }
+    }
+
+    pub fn canvas(name: &str) -> Self {
+        Self::new(name, LayerKind::Canvas)
+    }
+
+    pub fn webgl(name: &str) -> Self {
+        Self::new(name, LayerKind::WebGL)
+    }
+}
+
+/// Drawing target backing a layer.
+///
+/// Surfaces created by the application from DOM canvases are resized together with
+/// the main canvas, headless ones are provided through `Application::set_surface`.
+pub struct Surface {
+    pub kind: LayerKind,
+    pub context_2d: Option<Rc<dyn Canvas2D>>,
+    pub context_gl: Option<ContextGL>,
+    pub(crate) canvas: Option<HtmlCanvasElement>,
+}
+
+impl Surface {
+    pub fn new_2d(context_2d: Rc<dyn Canvas2D>) -> Self {
+        Self { kind: LayerKind::Canvas, context_2d: Some(context_2d), context_gl: None, canvas: None }
+    }
+
+    pub fn new_gl(context_gl: ContextGL) -> Self {
+        Self { kind: LayerKind::WebGL, context_2d: None, context_gl: Some(context_gl), canvas: None }
+    }
+
+    pub(crate) fn with_canvas(mut self, canvas: HtmlCanvasElement) -> Self {
+        self.canvas = Some(canvas);
+        self
+    }
+}
@@ -9,6 +9,14 @@ pub mod component;
 pub mod section;
 pub mod content;
 pub mod input;
+pub mod canvas;
+pub mod testing;
+pub mod transition;
+pub mod layer;
+pub mod replay;
+pub mod drag;
+pub mod gesture;
+pub mod scroll;
 pub mod prelude;
 
 pub use crate::core::{Application, State, FpsCounter};
@@ -16,5 +24,6 @@ pub use crate::scene::Scene;
 pub use crate::container::Container;
 pub use crate::section::Section;
 pub use crate::content::Content;
+pub use crate::component::FrameTime;
 pub use crate::span::{Span, SpanTrait, TextSpan, WorldSpan};
 pub use dragon;
@@ -3,6 +3,7 @@ pub use crate::scene::Scene;
 pub use crate::container::Container;
 pub use crate::section::Section;
 pub use crate::content::Content;
+pub use crate::component::FrameTime;
 pub use crate::span::{Span, SpanTrait, TextSpan, WorldSpan};
 
 pub mod renderer {
@@ -16,6 +17,8 @@ pub mod renderer {
     };
 
     pub use crate::core::RendererContext;
+    pub use crate::canvas::Canvas2D;
+    pub use crate::layer::{Layer, LayerKind, Surface};
 }
 
 pub mod js {
@@ -0,0 +1,246 @@
+//! Recording of the input fed into an `Application`, and its deterministic replay.
+//!
+//! Logs are plain text with one timestamped input per line, e.g. `16 mouse_down 10 20 0 1`,
+//! so bug reports can carry them and tests can check them in.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::core::Application;
+use crate::component::{MouseButton, Modifiers};
+use crate::utils::unquote;
+
+/// One input as received by `Application`, buttons and modifiers kept in their numeric forms
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    MouseMove { x: f64, y: f64 },
+    MouseDown { x: f64, y: f64, button: MouseButton, modifiers: Modifiers },
+    MouseUp { x: f64, y: f64, button: MouseButton, modifiers: Modifiers },
+    Click { x: f64, y: f64, button: MouseButton, modifiers: Modifiers },
+    DoubleClick { x: f64, y: f64, button: MouseButton, modifiers: Modifiers },
+    ContextMenu { x: f64, y: f64, modifiers: Modifiers },
+    Wheel { x: f64, y: f64, delta_x: f64, delta_y: f64, modifiers: Modifiers },
+    PointerEnter { x: f64, y: f64 },
+    PointerLeave { x: f64, y: f64 },
+    KeyDown { key: String, modifiers: Modifiers },
+    KeyUp { key: String, modifiers: Modifiers },
+    TouchStart { id: i32, x: f64, y: f64 },
+    TouchMove { id: i32, x: f64, y: f64 },
+    TouchEnd { id: i32, x: f64, y: f64 },
+    TouchCancel { id: i32 },
+    /// Canvas size in logical pixels and its pixel ratio after a resize
+    Resize { w: u32, h: u32, ratio: f64 },
+    /// A tick of `dt` milliseconds, so time driven behavior replays identically
+    Tick { dt: f64 },
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputEvent::MouseMove { x, y } => write!(f, "mouse_move {} {}", x, y),
+            InputEvent::MouseDown { x, y, button, modifiers } =>
+                write!(f, "mouse_down {} {} {} {}", x, y, button.index(), modifiers.bits()),
+            InputEvent::MouseUp { x, y, button, modifiers } =>
+                write!(f, "mouse_up {} {} {} {}", x, y, button.index(), modifiers.bits()),
+            InputEvent::Click { x, y, button, modifiers } =>
+                write!(f, "click {} {} {} {}", x, y, button.index(), modifiers.bits()),
+            InputEvent::DoubleClick { x, y, button, modifiers } =>
+                write!(f, "double_click {} {} {} {}", x, y, button.index(), modifiers.bits()),
+            InputEvent::ContextMenu { x, y, modifiers } => write!(f, "context_menu {} {} {}", x, y, modifiers.bits()),
+            InputEvent::Wheel { x, y, delta_x, delta_y, modifiers } =>
+                write!(f, "wheel {} {} {} {} {}", x, y, delta_x, delta_y, modifiers.bits()),
+            InputEvent::PointerEnter { x, y } => write!(f, "pointer_enter {} {}", x, y),
+            InputEvent::PointerLeave { x, y } => write!(f, "pointer_leave {} {}", x, y),
+            InputEvent::KeyDown { key, modifiers } => write!(f, "key_down {} {:?}", modifiers.bits(), key),
+            InputEvent::KeyUp { key, modifiers } => write!(f, "key_up {} {:?}", modifiers.bits(), key),
+            InputEvent::TouchStart { id, x, y } => write!(f, "touch_start {} {} {}", id, x, y),
+            InputEvent::TouchMove { id, x, y } => write!(f, "touch_move {} {} {}", id, x, y),
+            InputEvent::TouchEnd { id, x, y } => write!(f, "touch_end {} {} {}", id, x, y),
+            InputEvent::TouchCancel { id } => write!(f, "touch_cancel {}", id),
+            InputEvent::Resize { w, h, ratio } => write!(f, "resize {} {} {}", w, h, ratio),
+            InputEvent::Tick { dt } => write!(f, "tick {}", dt),
+        }
+    }
+}
+
+impl FromStr for InputEvent {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (op, args) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, ""),
+        };
+
+        macro_rules! numbers {
+            ($n: expr) => {{
+                let fields: Vec<&str> = args.split_whitespace().collect();
+                if fields.len() != $n {
+                    return Err(format!("Expected {} numbers in {:?}", $n, line));
+                }
+                fields.iter()
+                    .map(|v| v.parse::<f64>().map_err(|_| format!("Invalid number {:?} in {:?}", v, line)))
+                    .collect::<Result<Vec<f64>, String>>()?
+            }};
+        }
+        let button = |index: f64| MouseButton::from_index(index as i16)
+            .ok_or_else(|| format!("Invalid button in {:?}", line));
+        let modifiers = |bits: f64| Modifiers::from_bits(bits as u8);
+        let key = || -> Result<(Modifiers, String), String> {
+            let mut parts = args.splitn(2, ' ');
+            let bits = parts.next().and_then(|v| v.parse::<u8>().ok());
+            match (bits, parts.next()) {
+                (Some(bits), Some(key)) => Ok((Modifiers::from_bits(bits), unquote(key)?)),
+                _ => Err(format!("Invalid key input {:?}", line)),
+            }
+        };
+
+        let event = match op {
+            "mouse_move" => { let v = numbers!(2); InputEvent::MouseMove { x: v[0], y: v[1] } },
+            "mouse_down" => {
+                let v = numbers!(4);
+                InputEvent::MouseDown { x: v[0], y: v[1], button: button(v[2])?, modifiers: modifiers(v[3]) }
+            },
+            "mouse_up" => {
+                let v = numbers!(4);
+                InputEvent::MouseUp { x: v[0], y: v[1], button: button(v[2])?, modifiers: modifiers(v[3]) }
+            },
+            "click" => {
+                let v = numbers!(4);
+                InputEvent::Click { x: v[0], y: v[1], button: button(v[2])?, modifiers: modifiers(v[3]) }
+            },
+            "double_click" => {
+                let v = numbers!(4);
+                InputEvent::DoubleClick { x: v[0], y: v[1], button: button(v[2])?, modifiers: modifiers(v[3]) }
+            },
+            "context_menu" => { let v = numbers!(3); InputEvent::ContextMenu { x: v[0], y: v[1], modifiers: modifiers(v[2]) } },
+            "wheel" => {
+                let v = numbers!(5);
+                InputEvent::Wheel { x: v[0], y: v[1], delta_x: v[2], delta_y: v[3], modifiers: modifiers(v[4]) }
+            },
+            "pointer_enter" => { let v = numbers!(2); InputEvent::PointerEnter { x: v[0], y: v[1] } },
+            "pointer_leave" => { let v = numbers!(2); InputEvent::PointerLeave { x: v[0], y: v[1] } },
+            "key_down" => { let (modifiers, key) = key()?; InputEvent::KeyDown { key, modifiers } },
+            "key_up" => { let (modifiers, key) = key()?; InputEvent::KeyUp { key, modifiers } },
+            "touch_start" => { let v = numbers!(3); InputEvent::TouchStart { id: v[0] as i32, x: v[1], y: v[2] } },
+            "touch_move" => { let v = numbers!(3); InputEvent::TouchMove { id: v[0] as i32, x: v[1], y: v[2] } },
+            "touch_end" => { let v = numbers!(3); InputEvent::TouchEnd { id: v[0] as i32, x: v[1], y: v[2] } },
+            "touch_cancel" => { let v = numbers!(1); InputEvent::TouchCancel { id: v[0] as i32 } },
+            "resize" => { let v = numbers!(3); InputEvent::Resize { w: v[0] as u32, h: v[1] as u32, ratio: v[2] } },
+            "tick" => { let v = numbers!(1); InputEvent::Tick { dt: v[0] } },
+            _ => return Err(format!("Unknown input {:?}", line)),
+        };
+        Ok(event)
+    }
+}
+
+/// An input with the time it was received at, in milliseconds since the recording started
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputRecord {
+    pub time: f64,
+    pub event: InputEvent,
+}
+
+/// Recorded inputs in the order they were received, serialized as one per line
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct InputLog {
+    pub records: Vec<InputRecord>,
+}
+
+impl InputLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, time: f64, event: InputEvent) {
+        self.records.push(InputRecord { time, event });
+    }
+
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let mut log = Self::new();
+        for line in data.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            let (time, event) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => return Err(format!("Missing input in {:?}", line)),
+            };
+            let time = time.parse::<f64>().map_err(|_| format!("Invalid time in {:?}", line))?;
+            log.push(time, event.parse::<InputEvent>()?);
+        }
+        Ok(log)
+    }
+
+    /// Time of the last record
+    pub fn duration(&self) -> f64 {
+        self.records.last().map(|record| record.time).unwrap_or(0.)
+    }
+}
+
+impl fmt::Display for InputLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for record in self.records.iter() {
+            writeln!(f, "{} {}", record.time, record.event)?;
+        }
+        Ok(())
+    }
+}
+
+/// Feeds a log into an application, at once or following its timestamps
+pub struct InputPlayer {
+    log: InputLog,
+    next: usize,
+}
+
+impl InputPlayer {
+    pub fn new(log: InputLog) -> Self {
+        Self { log, next: 0 }
+    }
+
+    /// Feed the records stamped up to `time` milliseconds, returns how many were fed
+    pub fn advance_to(&mut self, app: &mut Application, time: f64) -> usize {
+        let start = self.next;
+        while let Some(record) = self.log.records.get(self.next) {
+            if record.time > time {
+                break;
+            }
+            app.apply_input(&record.event);
+            self.next += 1;
+        }
+        self.next - start
+    }
+
+    /// Feed every remaining record
+    pub fn play_all(&mut self, app: &mut Application) -> usize {
+        self.advance_to(app, std::f64::INFINITY)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.log.records.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_round_trip() {
+        let mut log = InputLog::new();
+        log.push(0., InputEvent::Resize { w: 800, h: 600, ratio: 2. });
+        log.push(1.5, InputEvent::MouseMove { x: 10.5, y: 20. });
+        log.push(3., InputEvent::MouseDown { x: 10.5, y: 20., button: MouseButton::Secondary, modifiers: Modifiers::from_bits(5) });
+        log.push(4., InputEvent::Wheel { x: 1., y: 2., delta_x: 0., delta_y: -120., modifiers: Modifiers::default() });
+        log.push(5., InputEvent::KeyDown { key: "\"quoted\" key".to_string(), modifiers: Modifiers::from_bits(2) });
+        log.push(6., InputEvent::KeyUp { key: " ".to_string(), modifiers: Modifiers::default() });
+        log.push(8., InputEvent::TouchMove { id: 3, x: 4., y: -2. });
+        log.push(9., InputEvent::TouchCancel { id: 3 });
+        log.push(16., InputEvent::Tick { dt: 16. });
+        let text = log.to_string();
+        assert_eq!(InputLog::parse(&text).unwrap(), log);
+        assert!(InputLog::parse("1 jump 2").is_err());
+        assert!(InputLog::parse("1 mouse_move 1 2 3").is_err());
+        assert!(InputLog::parse("1 mouse_move 1").is_err());
+        assert!(InputLog::parse("1 tick").is_err());
+        assert!(InputLog::parse("1 key_down 0 \"a\" \"b\"").is_err());
+    }
+}
@@ -6,9 +6,11 @@ use crate::traits::*;
 use crate::container::{Scrollable, Container};
 use crate::section::*;
 use crate::content::Content;
-use crate::component::Event;
+use crate::component::{Event, EventPhase, FrameTime, Position, Rect};
 use crate::span::*;
 use crate::prelude::renderer::RendererContext;
+use crate::layer::{self, Layer};
+use crate::scroll::ScrollPhysics;
 
 
 pub struct Scene {
@@ -27,6 +29,9 @@ pub struct Scene {
     margin_min_y: f32,
     margin_max_y: f32,
 
+    // Stacking order of the layers the scene renders to, bottom first
+    layers: Vec<Layer>,
+
     state: State
 }
 
@@ -46,6 +51,7 @@ impl Scene {
             margin_max_x: 20.,
             margin_min_y: 10. ,
             margin_max_y: 20.,
+            layers: vec![Layer::canvas(layer::DEFAULT_LAYER)],
             state,
         }
     }
@@ -74,6 +80,7 @@ impl Scene {
             margin_max_x,
             margin_min_y,
             margin_max_y,
+            layers: vec![Layer::canvas(layer::DEFAULT_LAYER)],
             state,
         }
     }
@@ -85,12 +92,26 @@ impl Scene {
     }
 
     pub fn render_tick(&self, ctx: &RendererContext) {
-        self.draw_outline(ctx);
+        if ctx.is_layer(layer::DEFAULT_LAYER) {
+            self.draw_outline(ctx);
+        }
         self.container.render_tick(ctx);
     }
+
+    /// Declare the layers of the scene from bottom to top, e.g. 2d ui, WebGL world and 2d overlay
+    pub fn set_layers(&mut self, layers: Vec<Layer>) {
+        self.layers = layers;
+    }
+
+    pub fn get_layers(&self) -> &[Layer] {
+        &self.layers
+    }
     
-    pub fn tick(&mut self) {
-        self.container.tick();
+    pub fn tick(&mut self, time: &FrameTime) {
+        if self.container.advance_scroll(time.dt) {
+            self.state.borrow().invalidate_all();
+        }
+        self.container.tick(time);
     }
 
     pub fn on_resize(&mut self, meta: &CanvasMeta) {
@@ -104,14 +125,119 @@ impl Scene {
         self.container.on_resize(self.x, self.y, self.w, self.h);
     }
    
+    /// Scroll the items of the scene, returns how far they moved
+    pub fn scroll_by(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let moved = self.container.scroll_by(dx, dy);
+        if moved != (0., 0.) {
+            self.state.borrow().invalidate_all();
+        }
+        moved
+    }
+
+    pub fn get_scroll(&self) -> (f64, f64) {
+        self.container.get_scroll()
+    }
+
+    /// Keep scrolling with momentum after pointer drags, see `Section::set_kinetic`
+    pub fn set_kinetic(&mut self, physics: Option<ScrollPhysics>) {
+        self.container.set_kinetic(physics);
+    }
+
+    pub fn drag_scroll_by(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let moved = self.container.drag_by(dx, dy);
+        if moved != (0., 0.) {
+            self.state.borrow().invalidate_all();
+        }
+        moved
+    }
+
+    pub fn grab_scroll(&mut self) {
+        self.container.grab();
+    }
+
+    pub fn release_scroll(&mut self) {
+        self.container.release();
+    }
+
+    /// Scroll the scene and the sections within it so the item called `name` is visible,
+    /// returns false if the scene has no such item
+    pub fn scroll_into_view(&mut self, name: &str) -> bool {
+        let found = self.container.scroll_into_view(name);
+        if found {
+            self.state.borrow().invalidate_all();
+        }
+        found
+    }
+
     fn consume_event(&mut self, _ev: &mut Event) {
     }
 
+    /// Areas of the canvas showing the region, invalidated by items laid out within the
+    /// scene, once moved by the scrolling containers around them
+    pub fn scrolled_damage(&self, region: &Rect) -> Vec<Rect> {
+        let mut damage = Vec::new();
+        self.container.scrolled_damage(region, (0., 0.), &mut damage);
+        damage
+    }
+
+    /// Sections and spans under the position, outermost first, following the topmost item at each level
+    pub fn hit_path(&self, pos: &Position) -> Vec<Content> {
+        let mut path = Vec::new();
+        if pos.in_rec(self.x, self.y, self.w, self.h) {
+            self.container.hit_path(pos, &mut path);
+        }
+        path
+    }
+
+    /// Paths to every focusable span, sections leading to it first, in tab order
+    pub fn focusable_paths(&self) -> Vec<Vec<Content>> {
+        let mut paths = Vec::new();
+        self.container.focusable_paths(&mut Vec::new(), &mut paths);
+        paths
+    }
+
+    /// Propagate the event from the scene down to the deepest node under the pointer and back up.
+    ///
+    /// The sections under the pointer see it in the capture phase, outermost first, then the
+    /// spans under the innermost one are targets, topmost first, or that section itself when
+    /// it has none, then the sections see it bubble up. The route is collected beforehand and
+    /// no section stays borrowed while listeners run, so they can update any of them.
     pub fn dispatch_event(&mut self, ev: &mut Event) {
-        if ev.pos.in_rec(self.x, self.y, self.w, self.h) {
-            self.container.dispatch_event(ev);
-            self.consume_event(ev);
+        if !ev.pos.in_rec(self.x, self.y, self.w, self.h) {
+            return;
+        }
+        let mut sections = Vec::new();
+        let mut targets = self.container.event_route(&ev.pos, &mut sections);
+        if targets.is_empty() {
+            match sections.pop() {
+                Some(section) => targets.push(section),
+                None => {
+                    ev.phase = EventPhase::Target;
+                    self.consume_event(ev);
+                    return;
+                }
+            }
+        }
+        for section in sections.iter() {
+            section.deliver_event(ev, EventPhase::Capture, &self.state);
+            if ev.consumed {
+                return;
+            }
+        }
+        for target in targets.iter() {
+            target.deliver_event(ev, EventPhase::Target, &self.state);
+            if ev.consumed {
+                return;
+            }
+        }
+        for section in sections.iter().rev() {
+            section.deliver_event(ev, EventPhase::Bubble, &self.state);
+            if ev.consumed {
+                return;
+            }
         }
+        ev.phase = EventPhase::Bubble;
+        self.consume_event(ev);
     }
 
 
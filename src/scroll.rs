@@ -0,0 +1,100 @@
+//! Momentum and overscroll of scrolling containers dragged with the pointer.
+//!
+//! Released drags keep moving with the velocity measured over the last tick, decaying
+//! exponentially, and offsets pulled past the edges of the items bounce back to them.
+//! Stepping integrates the curves exactly, so the motion does not depend on the frame rate.
+
+// Velocity in pixels per millisecond under which the items stop
+const MIN_VELOCITY: f64 = 0.01;
+// Distance to the edge under which a bounce snaps to it
+const MIN_OVERSCROLL: f64 = 0.5;
+
+/// How a kinetic container moves once released
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScrollPhysics {
+    /// Time constant of the momentum in milliseconds, after which the velocity fell to 37%
+    pub decay: f64,
+    /// Share of the pointer movement applied past the edges while dragging
+    pub resistance: f64,
+    /// Farthest the items may go past the edges
+    pub max_overscroll: f64,
+    /// Time constant of the bounce back to the edges in milliseconds
+    pub bounce: f64,
+}
+
+impl Default for ScrollPhysics {
+    fn default() -> Self {
+        Self { decay: 325., resistance: 0.5, max_overscroll: 80., bounce: 100. }
+    }
+}
+
+impl ScrollPhysics {
+    /// Offset after dragging the items by `d` from `offset`, resisting past the edges
+    pub fn drag(&self, offset: f64, max: f64, d: f64) -> f64 {
+        let target = offset + d;
+        let d = if target < 0. || target > max || offset < 0. || offset > max { d * self.resistance } else { d };
+        self.limit(offset + d, max)
+    }
+
+    /// Offset and velocity `dt` milliseconds later, for items released at `offset` moving at `velocity`
+    pub fn step(&self, offset: f64, velocity: f64, max: f64, dt: f64) -> (f64, f64) {
+        let edge = if offset < 0. { 0. } else if offset > max { max } else { offset };
+        let outward = offset != edge && (offset - edge).signum() == velocity.signum();
+        let (offset, velocity) = if offset == edge {
+            Self::coast(offset, velocity, self.decay, dt)
+        } else if outward && velocity != 0. {
+            // Momentum carried past the edge is braked hard, the bounce takes over from there
+            Self::coast(offset, velocity, self.bounce / 4., dt)
+        } else {
+            let offset = edge + (offset - edge) * (-dt / self.bounce).exp();
+            let offset = if (offset - edge).abs() < MIN_OVERSCROLL { edge } else { offset };
+            (offset, 0.)
+        };
+        let limited = self.limit(offset, max);
+        let velocity = if velocity.abs() < MIN_VELOCITY || limited != offset { 0. } else { velocity };
+        (limited, velocity)
+    }
+
+    /// Whether the items are at rest at `offset`
+    pub fn is_settled(&self, offset: f64, velocity: f64, max: f64) -> bool {
+        velocity == 0. && offset >= 0. && offset <= max
+    }
+
+    // Exponentially decaying motion, integrated over `dt`
+    fn coast(offset: f64, velocity: f64, decay: f64, dt: f64) -> (f64, f64) {
+        let k = (-dt / decay).exp();
+        (offset + velocity * decay * (1. - k), velocity * k)
+    }
+
+    fn limit(&self, offset: f64, max: f64) -> f64 {
+        offset.max(-self.max_overscroll).min(max + self.max_overscroll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_momentum_decays_and_bounces() {
+        let physics = ScrollPhysics::default();
+        let (offset, velocity) = physics.step(0., 1., 1000., 325.);
+        assert!((velocity - (-1f64).exp()).abs() < 1e-9);
+        assert!((offset - 325. * (1. - (-1f64).exp())).abs() < 1e-9);
+        // Stepping in two halves lands in the same place
+        let (half, v) = physics.step(0., 1., 1000., 162.5);
+        let (twice, _) = physics.step(half, v, 1000., 162.5);
+        assert!((twice - offset).abs() < 1e-9);
+
+        assert_eq!(physics.drag(0., 100., -40.), -20.);
+        assert_eq!(physics.drag(50., 100., 20.), 70.);
+        assert_eq!(physics.drag(0., 100., -1000.), -80.);
+
+        let mut state = (-20., 0.);
+        for _ in 0..60 {
+            state = physics.step(state.0, state.1, 100., 16.);
+        }
+        assert_eq!(state, (0., 0.));
+        assert!(physics.is_settled(state.0, state.1, 100.));
+    }
+}
@@ -3,7 +3,10 @@ use crate::container::*;
 use crate::content::*;
 use crate::component::*;
 use crate::span::*;
-use crate::prelude::{js, renderer::RendererContext};
+use crate::prelude::renderer::{RendererContext, Canvas2D};
+use crate::layer;
+use crate::drag::{DragPayload, DropPredicate};
+use crate::scroll::ScrollPhysics;
 
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
@@ -13,6 +16,9 @@ use crate::core::State;
 pub type SectionRef = Rc<RefCell<Section>>;
 pub type SectionWeak = Weak<RefCell<Section>>;
 
+// Room around a cached layer for strokes straddling the section edges
+const LAYER_PADDING: f64 = 1.;
+
 
 pub struct Section {
     pub name: String,
@@ -27,6 +33,16 @@ pub struct Section {
     pub height: f32,
     pub order: u8,
 
+    visible: bool,
+    hovered: bool,
+
+    // Payloads the section accepts as a drop target
+    accept_drop: Option<DropPredicate>,
+
+    // Offscreen copy of the subtree on the default layer when the section is cached
+    cached: bool,
+    cache: RefCell<Option<Rc<dyn Canvas2D>>>,
+
     state: State
 }
 
@@ -42,6 +58,11 @@ impl Section {
             width,
             height,
             order: 0,
+            visible: true,
+            hovered: false,
+            accept_drop: None,
+            cached: false,
+            cache: RefCell::new(None),
             state,
         }))
     }
@@ -57,6 +78,11 @@ impl Section {
             width,
             height,
             order: 0,
+            visible: true,
+            hovered: false,
+            accept_drop: None,
+            cached: false,
+            cache: RefCell::new(None),
             state
         }))
     }
@@ -88,9 +114,51 @@ impl Section {
         self.container.register(Content::Span { span: Rc::new(RefCell::new(span)) });
     }
 
+    /// Remove the item called `name` from the section and lay out the remaining ones,
+    /// e.g. to register it in another section after a drop
+    pub fn take_item(&mut self, name: &str) -> Option<Content> {
+        let item = self.container.remove(name)?;
+        self.relayout();
+        Some(item)
+    }
+
+    /// Add an item taken from another section and lay the section out again
+    pub fn insert_item(&mut self, item: Content) {
+        self.container.register(item);
+        self.relayout();
+    }
+
+    // Lay the items out again within the current area
+    fn relayout(&mut self) {
+        self.cache.replace(None);
+        self.container.on_resize(self.x, self.y, self.w, self.h);
+        self.invalidate();
+    }
+
+    pub fn get_rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.w, self.h)
+    }
+
+    /// Redraw the section on the next frame
+    pub fn invalidate(&self) {
+        self.state.borrow().invalidate(self.get_rect());
+    }
+
+    /// Render the subtree once to an offscreen layer and blit it on later frames,
+    /// until something inside invalidates or the section is resized
+    pub fn set_cached(&mut self, cached: bool) {
+        self.cached = cached;
+        self.cache.replace(None);
+    }
+
+    pub fn is_cached(&self) -> bool {
+        self.cached
+    }
+
     pub fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool) {
         // log!(&format!("Resizing {}", &self.name));
         log!("Resizing {}", &self.name);
+        self.cache.replace(None);
         self.x = left;
         self.y = top;
         self.w = self.width as f64 * (right - left);
@@ -101,7 +169,7 @@ impl Section {
     
     fn draw_outline(&self, ctx: &RendererContext) {
         let ctx = &ctx.context_2d;
-        ctx.set_stroke_style(&js::JsValue::from_str("#07ce88"));
+        ctx.set_stroke_style("#07ce88");
         ctx.stroke_rect(self.x, self.y, self.w, self.h);
     }
 
@@ -112,22 +180,182 @@ impl Section {
     }
 
     pub fn render_tick(&self, ctx: &RendererContext) {
+        if !ctx.is_layer(layer::DEFAULT_LAYER) {
+            self.container.render_tick(ctx);
+            return;
+        }
+        if self.cached {
+            if let Some(cache) = self.render_cache(ctx) {
+                let (w, h) = (self.w + 2. * LAYER_PADDING, self.h + 2. * LAYER_PADDING);
+                ctx.context_2d.draw_layer(cache.as_ref(), self.x - LAYER_PADDING, self.y - LAYER_PADDING, w, h);
+                return;
+            }
+        }
         self.draw_outline(ctx);
         self.container.render_tick(ctx);
     }
 
-    pub fn tick(&mut self) {
-        self.container.tick();
+    // Fetch the cached subtree, redrawing it first if missing or invalidated
+    fn render_cache(&self, ctx: &RendererContext) -> Option<Rc<dyn Canvas2D>> {
+        let valid = !self.state.borrow().was_invalidated(&self.get_rect());
+        if valid {
+            if let Some(ref cache) = *self.cache.borrow() {
+                return Some(cache.clone());
+            }
+        }
+
+        // Offscreen surfaces are sized in device pixels and drawn into in logical ones
+        let ratio = ctx.pixel_ratio;
+        let (w, h) = ((self.w + 2. * LAYER_PADDING) * ratio, (self.h + 2. * LAYER_PADDING) * ratio);
+        let cache = match self.cache.borrow().clone() {
+            Some(cache) => cache,
+            None => ctx.context_2d.create_layer(w.ceil() as u32, h.ceil() as u32)?,
+        };
+        cache.clear_rect(0., 0., w, h);
+        cache.save();
+        cache.scale(ratio, ratio);
+        cache.translate(LAYER_PADDING - self.x, LAYER_PADDING - self.y);
+        let context = RendererContext { context_2d: cache.clone(), region: None, ..ctx.clone() };
+        self.draw_outline(&context);
+        self.container.render_tick(&context);
+        cache.restore();
+        self.cache.replace(Some(cache.clone()));
+        Some(cache)
+    }
+
+    pub fn tick(&mut self, time: &FrameTime) {
+        if self.container.advance_scroll(time.dt) {
+            self.invalidate();
+        }
+        self.container.tick(time);
+    }
+
+    /// Hidden sections and everything inside them are neither drawn nor hit by the pointer
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            self.invalidate();
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Scroll offset of the items, non zero only with a scrolling container
+    pub fn get_scroll(&self) -> (f64, f64) {
+        self.container.get_scroll()
+    }
+
+    /// Largest scroll offset along each axis
+    pub fn get_max_scroll(&self) -> (f64, f64) {
+        self.container.max_scroll()
+    }
+
+    /// Scroll to the offset, clamped to the items, returns how far they moved
+    pub fn set_scroll(&mut self, x: f64, y: f64) -> (f64, f64) {
+        let moved = self.container.scroll_to(x, y);
+        if moved != (0., 0.) {
+            self.invalidate();
+        }
+        moved
+    }
+
+    pub fn scroll_by(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let (x, y) = self.get_scroll();
+        self.set_scroll(x + dx, y + dy)
+    }
+
+    /// Keep scrolling with momentum after pointer drags and bounce back from the edges,
+    /// `None` to follow the pointer only
+    pub fn set_kinetic(&mut self, physics: Option<ScrollPhysics>) {
+        self.container.set_kinetic(physics);
+    }
+
+    /// Velocity of the items in pixels per millisecond
+    pub fn get_scroll_velocity(&self) -> (f64, f64) {
+        self.container.get_velocity()
+    }
+
+    /// Scroll along with the pointer dragging the items, returns how far they moved
+    pub fn drag_scroll_by(&mut self, dx: f64, dy: f64) -> (f64, f64) {
+        let moved = self.container.drag_by(dx, dy);
+        if moved != (0., 0.) {
+            self.invalidate();
+        }
+        moved
+    }
+
+    /// The pointer pressed the section, stopping its momentum
+    pub fn grab_scroll(&mut self) {
+        self.container.grab();
+    }
+
+    /// The pointer let the section go, which keeps scrolling with the velocity of the drag
+    pub fn release_scroll(&mut self) {
+        self.container.release();
+    }
+
+    /// Scroll the section and the ones within it so the item called `name` is visible,
+    /// returns false if the item is not inside the section
+    pub fn scroll_into_view(&mut self, name: &str) -> bool {
+        let found = self.container.scroll_into_view(name);
+        if found {
+            // Sections below may have scrolled even if this one did not
+            self.invalidate();
+        }
+        found
+    }
+
+    /// Make the section a drop target for the payloads `accept` returns true for
+    pub fn set_drop_target<F: 'static + Fn(&DragPayload) -> bool>(&mut self, accept: F) {
+        self.accept_drop = Some(Rc::new(accept));
+    }
+
+    pub fn clear_drop_target(&mut self) {
+        self.accept_drop = None;
+    }
+
+    /// Whether the section is a drop target for the payload
+    pub fn accepts_drop(&self, payload: &DragPayload) -> bool {
+        self.visible && self.accept_drop.as_ref().map(|accept| accept(payload)).unwrap_or(false)
+    }
+
+    /// Whether the pointer is over the section
+    pub fn is_hovered(&self) -> bool {
+        self.hovered
+    }
+
+    /// Append the items under the position within the section to `path`, topmost ones only
+    pub fn hit_path(&self, pos: &Position, path: &mut Vec<Content>) {
+        self.container.hit_path(pos, path);
+    }
+
+    /// Append the sections an event at the position passes through within the section to
+    /// `path`, returns the spans it targets, see `Container::event_route`
+    pub fn event_route(&self, pos: &Position, path: &mut Vec<Content>) -> Vec<Content> {
+        self.container.event_route(pos, path)
+    }
+
+    /// Append where the region shows within the section once scrolled, see `Container::scrolled_damage`
+    pub fn scrolled_damage(&self, region: &Rect, offset: (f64, f64), damage: &mut Vec<Rect>) {
+        self.container.scrolled_damage(region, offset, damage);
     }
 
-    fn consume_event(&mut self, ev: &mut Event) {
-        log!("mouse on {} ", self.name);
+    /// Append the paths to the focusable spans within the section to `paths`, in tab order
+    pub fn focusable_paths(&self, path: &mut Vec<Content>, paths: &mut Vec<Vec<Content>>) {
+        self.container.focusable_paths(path, paths);
     }
 
-    pub fn dispatch_event(&mut self, ev: &mut Event) {
-        if ev.pos.in_rec(self.x, self.y, self.w, self.h) {
-            self.container.dispatch_event(ev);
-            self.consume_event(ev);
+    /// Update the section for an event delivered to it in any phase, e.g. pointer enter and leave.
+    ///
+    /// Listeners are left to the caller, `Content::deliver_event` runs them once the
+    /// section is released.
+    pub fn receive_event(&mut self, ev: &mut Event) {
+        match ev.ev {
+            EventType::PointerEnter => self.hovered = true,
+            EventType::PointerLeave => self.hovered = false,
+            _ => {},
         }
     }
 }
@@ -4,19 +4,39 @@ use std::cell::RefCell;
 use std::any::Any;
 use crate::component::*;
 use crate::prelude::renderer::RendererContext;
+use crate::layer::DEFAULT_LAYER;
+use crate::drag::DragPayload;
 
 pub type SpanRef = Rc<RefCell<Span>>;
 pub type SpanWeak = Weak<RefCell<Span>>;
 
+/// A leaf of the UI tree.
+///
+/// Spans changing how they look outside of layout should invalidate their area through
+/// `StateProto::invalidate`, only invalidated areas are redrawn. Spans that invalidate
+/// themselves should keep the handle from `StateProto::get_dirty` rather than the state,
+/// their owners may be called while the state is borrowed.
 pub trait SpanTrait {
     fn get_name(&self) -> &str;
     fn dispatch_event(&mut self, _ev: &mut Event) {}
     fn dispatch(&mut self, _data: Box<dyn Any>) {}
     fn draw(&self, _ctx: &RendererContext) {}
-    fn tick(&mut self) {}
+    fn tick(&mut self, _time: &FrameTime) {}
     fn render_tick(&self, _ctx: &RendererContext) {}
     fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool);
     fn get_order(&self) -> u8 { 0 }
+    /// Area occupied by the span, `None` if it may draw anywhere in its container
+    fn get_rect(&self) -> Option<Rect> { None }
+    /// Hidden spans are neither drawn nor hit by the pointer
+    fn is_visible(&self) -> bool { true }
+    /// Whether clicking or tabbing can give the span the keyboard focus
+    fn is_focusable(&self) -> bool { false }
+    /// Name of the scene layer the span renders to
+    fn get_layer(&self) -> &str { DEFAULT_LAYER }
+    /// Payload carried when the span is dragged, `None` if it cannot be dragged
+    fn drag_payload(&self) -> Option<DragPayload> { None }
+    /// Whether the span is a drop target for the payload
+    fn accepts_drop(&self, _payload: &DragPayload) -> bool { false }
 }
 
 // pub type Span = Box<dyn SpanTrait<Data = dyn Any>>;
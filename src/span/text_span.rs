@@ -1,10 +1,9 @@
 use std::cell::RefCell;
 use std::any::Any;
-use wasm_bindgen::prelude::*;
 
-use crate::core::State;
+use crate::core::{State, Dirty};
 use crate::span::SpanTrait;
-use crate::component::Event;
+use crate::component::Rect;
 use crate::utils;
 use crate::prelude::renderer::RendererContext;
 
@@ -20,12 +19,13 @@ pub struct TextSpan {
     pub width: f32,
     pub height: f32,
 
-    state: State,
+    dirty: Dirty,
     font_cache: RefCell<Option<String>>, // Caching proper font for the string
 }
 
 impl TextSpan {
     pub fn new(state: State, name: &str, text: &str, width: f32, height: f32) -> Self {
+        let dirty = state.borrow().get_dirty();
         Self {
             name: name.to_string(),
             text: text.to_string(),
@@ -36,13 +36,18 @@ impl TextSpan {
 
             width,
             height,
-            state,
+            dirty,
             font_cache: RefCell::new(None),
         }
     }
 
     pub fn set_text(&mut self, text: &str) {
         self.text = text.to_string();
+        self.invalidate();
+    }
+
+    fn invalidate(&self) {
+        self.dirty.invalidate(Rect::new(self.x, self.y, self.w, self.h));
     }
 }
 
@@ -54,10 +59,14 @@ impl SpanTrait for TextSpan {
 
     fn dispatch(&mut self, data: Box<dyn Any>) {
         if let Ok(text) = data.downcast::<String>() {
-            self.text = text.to_string();
+            self.set_text(&text);
         }
     }
 
+    fn get_rect(&self) -> Option<Rect> {
+        Some(Rect::new(self.x, self.y, self.w, self.h))
+    }
+
     /// Deprecated
     fn draw(&self, ctx: &RendererContext) {
         self.render_tick(ctx);
@@ -67,15 +76,15 @@ impl SpanTrait for TextSpan {
         let ctx = &ctx.context_2d;
         let mut font = self.font_cache.borrow_mut();
         if font.is_none() {
-            let size = utils::get_font_with_limit(ctx, &self.text, (self.w * 0.8).min(100.), "Arial").min(20).max(10);
+            let size = utils::get_font_with_limit(ctx.as_ref(), &self.text, (self.w * 0.8).min(100.), "Arial").min(20).max(10);
             *font = Some(format!("{}px {}", size, "Arial"));
         }
         if !font.is_none() {
             ctx.set_font(font.as_ref().unwrap());
             ctx.set_text_align("center");
             ctx.set_text_baseline("middle");
-            ctx.set_fill_style(&JsValue::from_str("white"));
-            let _ = ctx.fill_text(&self.text, self.x + self.w/2., self.y + self.h/2.);
+            ctx.set_fill_style("white");
+            ctx.fill_text(&self.text, self.x + self.w/2., self.y + self.h/2.);
         }
 
     }
@@ -6,11 +6,14 @@ use std::cell::RefCell;
 use std::any::Any;
 use dragon::*;
 
-use crate::core::State;
+use crate::core::{State, Dirty};
 use crate::span::SpanTrait;
-use crate::component::Event;
-use crate::prelude::{renderer::RendererContext, js::JsValue};
+use crate::component::{Event, FrameTime, FixedStep, Rect};
+use crate::prelude::renderer::RendererContext;
+use crate::layer::DEFAULT_LAYER;
 
+/// Default length of one step of the world simulation in milliseconds
+pub const WORLD_STEP: f64 = 1000. / 60.;
 
 pub struct WorldSpan {
     pub name: String,
@@ -24,8 +27,14 @@ pub struct WorldSpan {
     pub width: f32,
     pub height: f32,
     pub world: World,
+    layer: String,
 
-    state: State,
+    // Steps of the world when the application ticks with the frame time, and the time
+    // stepped so far
+    step: FixedStep,
+    world_time: f64,
+
+    dirty: Dirty,
     font_cache: RefCell<Option<String>>, // Caching proper font for the string
 }
 
@@ -38,6 +47,7 @@ impl WorldSpan {
         width: f32,
         height: f32
     ) -> Self {
+        let dirty = state.borrow().get_dirty();
         let world = World::new();
         world.attach_default_camera();
 
@@ -55,7 +65,10 @@ impl WorldSpan {
             width,
             height,
             world,
-            state,
+            layer: DEFAULT_LAYER.to_string(),
+            step: FixedStep::new(WORLD_STEP),
+            world_time: 0.,
+            dirty,
             font_cache: RefCell::new(None),
         }
     }
@@ -64,9 +77,28 @@ impl WorldSpan {
         self.text = text.to_string();
     }
 
+    /// Render to another scene layer, `ctx` given to `new` should come from
+    /// `Application::get_layer_context` for that layer
+    pub fn set_layer(&mut self, layer: &str) {
+        self.layer = layer.to_string();
+    }
+
+    /// Simulate the world in steps of `step` milliseconds. Under a fixed timestep of the
+    /// application the world steps once per tick with it instead.
+    pub fn set_step(&mut self, step: f64) {
+        if step > 0. {
+            self.step = FixedStep::new(step);
+        }
+    }
+
+    /// Time the world was simulated for, a whole number of steps
+    pub fn get_world_time(&self) -> f64 {
+        self.world_time
+    }
+
     fn draw_outline(&self, ctx: &RendererContext) {
         let ctx = &ctx.context_2d;
-        ctx.set_stroke_style(&JsValue::from_str("white"));
+        ctx.set_stroke_style("white");
         ctx.stroke_rect(self.x, self.y, self.w, self.h);
     }
 
@@ -87,12 +119,34 @@ impl SpanTrait for WorldSpan {
         }
     }
 
+    fn get_rect(&self) -> Option<Rect> {
+        Some(Rect::new(self.x, self.y, self.w, self.h))
+    }
+
+    fn get_layer(&self) -> &str {
+        &self.layer
+    }
+
     fn render_tick(&self, _ctx: &RendererContext) {
         self.world.state.render_tick();
     }
     
-    fn tick(&mut self) {
-        self.world.state.tick();
+    fn tick(&mut self, time: &FrameTime) {
+        // Step the world by the time passed rather than once per tick, so it moves at the
+        // same pace whatever the frame rate. Fixed ticks are steps already.
+        let (steps, step) = if time.fixed {
+            (1, time.dt)
+        } else {
+            (self.step.advance(time.dt), self.step.get_step())
+        };
+        for _ in 0..steps {
+            self.world.state.tick();
+            self.world_time += step;
+        }
+        // The world moved, redraw it
+        if steps > 0 {
+            self.dirty.invalidate(Rect::new(self.x, self.y, self.w, self.h));
+        }
     }
 
     /*
@@ -131,3 +185,36 @@ impl SpanTrait for WorldSpan {
 
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use crate::core::{Application, CanvasMeta};
+    use crate::canvas::NullCanvas;
+
+    #[test]
+    fn test_world_steps_follow_dt() {
+        let app = Application::new_headless(CanvasMeta::new(100, 100), Rc::new(NullCanvas::new()));
+        let ctx = app.get_layer_context(DEFAULT_LAYER).unwrap();
+        let mut span = WorldSpan::new(app.get_state(), ctx, "world", "", 1., 1.);
+        span.set_step(10.);
+        let tick = |span: &mut WorldSpan, dt: f64| span.tick(&FrameTime { dt, ..FrameTime::default() });
+
+        tick(&mut span, 25.);
+        assert_eq!(span.get_world_time(), 20.);
+        // The remainder carries over to the next tick
+        tick(&mut span, 5.);
+        assert_eq!(span.get_world_time(), 30.);
+        tick(&mut span, 0.);
+        assert_eq!(span.get_world_time(), 30.);
+        // Half the frame rate, same pace
+        tick(&mut span, 40.);
+        assert_eq!(span.get_world_time(), 70.);
+
+        // Fixed ticks of the application step the world once each
+        span.tick(&FrameTime { dt: 16., fixed: true, ..FrameTime::default() });
+        assert_eq!(span.get_world_time(), 86.);
+        tick(&mut span, 5.);
+        assert_eq!(span.get_world_time(), 86.);
+    }
+}
@@ -4,11 +4,11 @@ use dragon::core::*;
 use std::any::Any;
 use std::cmp::PartialOrd;
 use std::f64::consts::PI;
-use crate::prelude::{js::JsValue, renderer::RendererContext, renderer::Context2D };
+use crate::prelude::renderer::{RendererContext, Canvas2D};
 
 pub struct RenderingSystem {
     state: Rc<WorldState>,
-    ctx: Context2D,
+    ctx: Rc<dyn Canvas2D>,
     viewport: Matrix4<f32>,
 }
 
@@ -55,7 +55,7 @@ impl System for RenderingSystem {
                     let count = data.vertices.len() - 1;
                     let mut cut_at = cutter.next().unwrap_or(&count);
                     // Simplified rendering to canvas 2d context 
-                    self.ctx.set_stroke_style(&JsValue::from_str("white"));
+                    self.ctx.set_stroke_style("white");
                     self.ctx.begin_path();
                     let mut first = true;
                     for (index, vertex) in data.vertices.iter().enumerate() {
@@ -92,10 +92,10 @@ impl System for RenderingSystem {
                         match brush {
                             Brush::Lines { stroke, fill, vertices, action } => {
                                 if let Some(stroke) = stroke {
-                                    self.ctx.set_stroke_style(&JsValue::from_str(&stroke));
+                                    self.ctx.set_stroke_style(&stroke);
                                 }
                                 if let Some(fill) = fill {
-                                    self.ctx.set_fill_style(&JsValue::from_str(&fill));
+                                    self.ctx.set_fill_style(&fill);
                                 }
                                 self.ctx.begin_path();
                                 let mut first = true;
@@ -117,15 +117,15 @@ impl System for RenderingSystem {
                             },
                             Brush::Sphere { stroke, fill, center, radius, action } => {
                                 if let Some(stroke) = stroke {
-                                    self.ctx.set_stroke_style(&JsValue::from_str(&stroke));
+                                    self.ctx.set_stroke_style(&stroke);
                                 }
                                 if let Some(fill) = fill {
-                                    self.ctx.set_fill_style(&JsValue::from_str(&fill));
+                                    self.ctx.set_fill_style(&fill);
                                 }
                                 self.ctx.begin_path();
                                 let point = mvp.transform_point(center);
                                 let radius = project_size!(*radius, &translation);
-                                let _ = self.ctx.arc(point.x as f64, point.y as f64, radius as f64, 0., PI*2.);
+                                self.ctx.arc(point.x as f64, point.y as f64, radius as f64, 0., PI*2.);
                                 if action & 0x01 > 0 {
                                     self.ctx.fill();
                                 }
@@ -143,8 +143,8 @@ impl System for RenderingSystem {
             polygons.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
             for p in polygons.iter().rev() {
                 self.ctx.begin_path();
-                self.ctx.set_fill_style(&JsValue::from_str(&p.4));
-                self.ctx.set_stroke_style(&JsValue::from_str(&p.4));
+                self.ctx.set_fill_style(&p.4);
+                self.ctx.set_stroke_style(&p.4);
                 self.ctx.move_to(p.1.x as f64, p.1.y as f64);
                 self.ctx.line_to(p.2.x as f64, p.2.y as f64);
                 self.ctx.line_to(p.3.x as f64, p.3.y as f64);
@@ -155,7 +155,7 @@ impl System for RenderingSystem {
 
             // Render shapes
             for shape in shape_store.iter() {
-                self.ctx.set_stroke_style(&JsValue::from_str("white"));
+                self.ctx.set_stroke_style("white");
                 match shape {
                     Shape::Line { begin, end } => {
                         let begin = vp.transform_point(begin);
@@ -168,7 +168,7 @@ impl System for RenderingSystem {
                     Shape::Circle { center, radius } => {
                         let center = vp.transform_point(&Point3::from(*center.translation()));
                         self.ctx.begin_path();
-                        let _ = self.ctx.ellipse(
+                        self.ctx.ellipse(
                             center.x as f64,
                             center.y as f64,
                             *radius as f64,
@@ -183,8 +183,8 @@ impl System for RenderingSystem {
             let widgets = c_store.get::<WidgetComponent>();
             self.ctx.set_text_align("center");
             self.ctx.set_text_baseline("middle");
-            self.ctx.set_fill_style(&JsValue::from_str("grey"));
-            self.ctx.set_stroke_style(&JsValue::from_str("darkgreen"));
+            self.ctx.set_fill_style("grey");
+            self.ctx.set_stroke_style("darkgreen");
             for (entity, widget) in widgets.iter() {
                 let translate = transforms.get(entity).unwrap().position();
                 let position = vp.transform_point(&translate);
@@ -192,13 +192,13 @@ impl System for RenderingSystem {
                 match widget {
                     Widget::Text { ref translation, ref text } => {
                         let center = position + translation * scale;
-                        let _ = self.ctx.fill_text(text, center.x as f64, center.y as f64);
+                        self.ctx.fill_text(text, center.x as f64, center.y as f64);
                     },
                     Widget::FramedText { ref translation, ref text, width, height } => {
                         let w = width * scale;
                         let h = height * scale;
                         let center = position + translation * project_size!(1f32 , &translate);
-                        let _ = self.ctx.fill_text(text, center.x as f64, center.y as f64);
+                        self.ctx.fill_text(text, center.x as f64, center.y as f64);
                         self.ctx.rect((center.x - w / 2.) as f64, (center.y - h /2.) as f64, w as f64, h as f64);
                         self.ctx.stroke();
                     }
@@ -8,7 +8,7 @@ use crate::prelude::{js::JsValue, renderer::RendererContext, renderer::ContextGL
 
 pub struct RenderingSystem {
     state: Rc<WorldState>,
-    ctx: ContextGL,
+    ctx: Option<ContextGL>,
     viewport: Matrix4<f32>,
 }
 
@@ -1,11 +1,11 @@
 use std::rc::Rc;
 use dragon::ecs::*;
 use dragon::core::*;
-use crate::prelude::{js::JsValue, renderer::RendererContext, renderer::Context2D };
+use crate::prelude::renderer::{RendererContext, Canvas2D};
 
 pub struct RenderingSystem {
     state: Rc<WorldState>,
-    ctx: Context2D,
+    ctx: Rc<dyn Canvas2D>,
     viewport: Matrix4<f32>,
 }
 
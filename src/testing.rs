@@ -0,0 +1,99 @@
+//! Helpers for snapshot testing applications rendered into a `RecordingCanvas`
+//! or a `RasterCanvas`.
+//!
+//! Command golden files hold one draw command per line, image golden files are PNGs.
+//! Setting `WAND_BLESS=1` writes them from the current frame, otherwise a missing golden
+//! file fails the assertion so a deleted or misnamed one cannot pass silently.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::core::Application;
+use crate::canvas::{RecordingCanvas, RasterCanvas, Frame};
+
+/// Render one frame of the application and return the draw calls it issued
+pub fn record_frame(app: &Application, canvas: &RecordingCanvas) -> Frame {
+    canvas.clear();
+    app.render_tick();
+    canvas.take_frame()
+}
+
+/// Render one frame of the application and return it encoded as PNG
+pub fn raster_frame(app: &Application, canvas: &RasterCanvas) -> Vec<u8> {
+    app.render_tick();
+    canvas.to_png()
+}
+
+// Write the golden file when blessing, returns false if it is to be compared against
+fn bless_golden(path: &Path, data: &[u8]) -> bool {
+    let bless = env::var("WAND_BLESS").map(|v| v == "1").unwrap_or(false);
+    if bless {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("Failed to create golden directory");
+        }
+        fs::write(path, data).expect("Failed to write golden file");
+        return true;
+    }
+    if !path.exists() {
+        panic!("Missing golden file {}\nRun with WAND_BLESS=1 to create it.", path.display());
+    }
+    false
+}
+
+/// Compare a frame against the golden file at `path`, panicking with a diff on mismatch
+pub fn assert_golden<P: AsRef<Path>>(path: P, frame: &Frame) {
+    let path = path.as_ref();
+    let actual = frame.to_string();
+    if bless_golden(path, actual.as_bytes()) {
+        return;
+    }
+
+    let expected = fs::read_to_string(path).expect("Failed to read golden file");
+    if let Some(diff) = diff_frames(&expected, &actual) {
+        panic!("Frame does not match golden file {}\n{}\nRun with WAND_BLESS=1 to update it.", path.display(), diff);
+    }
+}
+
+/// Line diff of two serialized frames, `None` if they are identical
+pub fn diff_frames(expected: &str, actual: &str) -> Option<String> {
+    let expected: Vec<&str> = expected.lines().filter(|l| !l.trim().is_empty()).collect();
+    let actual: Vec<&str> = actual.lines().filter(|l| !l.trim().is_empty()).collect();
+    if expected == actual {
+        return None;
+    }
+
+    let mut diff = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {},
+            (e, a) => {
+                if let Some(e) = e {
+                    diff.push_str(&format!("{:>5} - {}\n", i + 1, e));
+                }
+                if let Some(a) = a {
+                    diff.push_str(&format!("{:>5} + {}\n", i + 1, a));
+                }
+            }
+        }
+    }
+    Some(diff)
+}
+
+/// Compare a PNG frame against the golden image at `path`.
+///
+/// On mismatch the actual frame is written next to it with an `.actual.png` suffix.
+pub fn assert_golden_png<P: AsRef<Path>>(path: P, png: &[u8]) {
+    let path = path.as_ref();
+    if bless_golden(path, png) {
+        return;
+    }
+
+    let expected = fs::read(path).expect("Failed to read golden image");
+    if expected != png {
+        let actual = path.with_extension("actual.png");
+        fs::write(&actual, png).expect("Failed to write actual image");
+        panic!("Frame does not match golden image {}, see {}\nRun with WAND_BLESS=1 to update it.",
+            path.display(), actual.display());
+    }
+}
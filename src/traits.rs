@@ -1,6 +1,6 @@
 use crate::scene::Scene;
 use crate::container::Container;
-use crate::prelude::{js, renderer::RendererContext};
+use crate::prelude::renderer::RendererContext;
 
 pub trait DrawOutline {
     fn draw_outline(&self, ctx: &RendererContext);
@@ -12,7 +12,7 @@ macro_rules! impl_draw_outline {
         impl DrawOutline for $type {
             fn draw_outline(&self, ctx: &RendererContext) {
                 let ctx = &ctx.context_2d;
-                ctx.set_stroke_style(&js::JsValue::from_str("#07ce88"));
+                ctx.set_stroke_style("#07ce88");
                 ctx.stroke_rect(self.x, self.y, self.w, self.h);
             }
         }
@@ -21,7 +21,7 @@ macro_rules! impl_draw_outline {
         impl<$t: ContentItem> DrawOutline for $type {
             fn draw_outline(&self, ctx: &RendererContext) {
                 let ctx = &ctx.context_2d;
-                ctx.set_stroke_style(&js::JsValue::from_str("white"));
+                ctx.set_stroke_style("white");
                 ctx.stroke_rect(self.x, self.y, self.w, self.h);
             }
         }
@@ -34,7 +34,7 @@ impl_draw_outline!(Scene);
 impl DrawOutline for Container {
     fn draw_outline(&self, ctx: &RendererContext) {
         let ctx = &ctx.context_2d;
-        ctx.set_stroke_style(&js::JsValue::from_str("white"));
+        ctx.set_stroke_style("white");
         ctx.stroke_rect(self.left, self.top, self.right - self.left, self.bottom - self.top);
     }
 }
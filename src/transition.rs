@@ -0,0 +1,111 @@
+use crate::scene::Scene;
+use crate::prelude::renderer::RendererContext;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TransitionKind {
+    /// Swap scenes instantly
+    None,
+    /// Fade the old scene out, then the new one in
+    Fade,
+    /// Both scenes move left, the new one entering from the right
+    SlideLeft,
+    /// Both scenes move right, the new one entering from the left
+    SlideRight,
+    /// Both scenes move up, the new one entering from the bottom
+    SlideUp,
+    /// Blend the old scene into the new one
+    CrossDissolve,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    /// Duration in milliseconds
+    pub duration: f64,
+}
+
+impl Transition {
+    pub fn new(kind: TransitionKind, duration: f64) -> Self {
+        Self { kind, duration }
+    }
+
+    pub fn none() -> Self {
+        Self::new(TransitionKind::None, 0.)
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.kind == TransitionKind::None || self.duration <= 0.
+    }
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// A transition in progress from the scene at `from` to the active scene
+pub struct ActiveTransition {
+    pub from: String,
+    pub transition: Transition,
+    elapsed: f64,
+}
+
+impl ActiveTransition {
+    pub fn new(from: &str, transition: Transition) -> Self {
+        Self {
+            from: from.to_string(),
+            transition,
+            elapsed: 0.,
+        }
+    }
+
+    /// Advance by `dt` milliseconds, returns true once the transition is over
+    pub fn advance(&mut self, dt: f64) -> bool {
+        self.elapsed += dt;
+        self.is_done()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.transition.duration
+    }
+
+    /// Eased progress between 0 and 1
+    pub fn progress(&self) -> f64 {
+        let p = (self.elapsed / self.transition.duration).max(0.).min(1.);
+        p * p * (3. - 2. * p)
+    }
+
+    /// Draw both scenes for the current progress, `w` and `h` being the canvas size
+    pub fn render_tick(&self, from: &Scene, to: &Scene, ctx: &RendererContext, w: f64, h: f64) {
+        let p = self.progress();
+        let (from_alpha, from_offset, to_alpha, to_offset) = match self.transition.kind {
+            TransitionKind::None => (0., (0., 0.), 1., (0., 0.)),
+            TransitionKind::Fade => {
+                if p < 0.5 {
+                    (1. - 2. * p, (0., 0.), 0., (0., 0.))
+                } else {
+                    (0., (0., 0.), 2. * p - 1., (0., 0.))
+                }
+            },
+            TransitionKind::CrossDissolve => (1. - p, (0., 0.), p, (0., 0.)),
+            TransitionKind::SlideLeft => (1., (-w * p, 0.), 1., (w * (1. - p), 0.)),
+            TransitionKind::SlideRight => (1., (w * p, 0.), 1., (-w * (1. - p), 0.)),
+            TransitionKind::SlideUp => (1., (0., -h * p), 1., (0., h * (1. - p))),
+        };
+        Self::render_scene(from, ctx, from_alpha, from_offset);
+        Self::render_scene(to, ctx, to_alpha, to_offset);
+    }
+
+    fn render_scene(scene: &Scene, ctx: &RendererContext, alpha: f64, offset: (f64, f64)) {
+        if alpha <= 0. {
+            return;
+        }
+        let canvas = &ctx.context_2d;
+        canvas.save();
+        canvas.set_global_alpha(alpha);
+        canvas.translate(offset.0, offset.1);
+        scene.render_tick(ctx);
+        canvas.restore();
+    }
+}
@@ -1,5 +1,8 @@
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::{SystemTime, UNIX_EPOCH};
-use crate::prelude::{js::*, renderer::{self, Context2D, TextMetrics}};
+#[cfg(target_arch = "wasm32")]
+use crate::prelude::{js::*, renderer};
+use crate::prelude::renderer::Canvas2D;
 
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -12,6 +15,7 @@ pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
@@ -22,6 +26,18 @@ extern "C" {
     pub fn info(s: &str);
 }
 
+// Console fallbacks so headless applications can run natively
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(s: &str) { println!("{}", s); }
+#[cfg(not(target_arch = "wasm32"))]
+pub fn warn(s: &str) { eprintln!("{}", s); }
+#[cfg(not(target_arch = "wasm32"))]
+pub fn error(s: &str) { eprintln!("{}", s); }
+#[cfg(not(target_arch = "wasm32"))]
+pub fn debug(s: &str) { println!("{}", s); }
+#[cfg(not(target_arch = "wasm32"))]
+pub fn info(s: &str) { println!("{}", s); }
+
 #[macro_export]
 macro_rules! log {
     ( $( $t:tt )* ) => {
@@ -53,19 +69,17 @@ macro_rules! debug {
     }
 }
 
-pub fn get_font_with_limit(ctx: &Context2D, text: &str, size: f64, font: &str) -> u32 {
+pub fn get_font_with_limit(ctx: &dyn Canvas2D, text: &str, size: f64, font: &str) -> u32 {
     let mut px = 5;
     if text.trim().len() < 1 {
         return px;
     }
 
     let mut style: String;
-    let mut res: Result<TextMetrics, JsValue>;
     for _ in 0..1000 {
         style = format!("{}px {}", px, font);
         ctx.set_font(&style);
-        res = ctx.measure_text(text);
-        if res.unwrap().width() >= size {
+        if ctx.measure_text(text) >= size {
             return px;
         }
         px += 2;
@@ -75,13 +89,43 @@ pub fn get_font_with_limit(ctx: &Context2D, text: &str, size: f64, font: &str) -
 
 #[allow(dead_code)]
 #[inline]
+#[cfg(target_arch = "wasm32")]
 pub fn now_ms() -> u128 {
     renderer::window().unwrap().performance().unwrap().now() as u128
-    /*
-    let now = web_sys::window().unwrap().performance().unwrap().now();
-    let secs = now as u64 / 1000;
-    let nanos = (now as u32 % 1000) * 1000000;
+}
+
+#[allow(dead_code)]
+#[inline]
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> u128 {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()
-    */
 }
 
+/// Parse a string literal written with `{:?}`
+pub(crate) fn unquote(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return Err(format!("Expected quoted string, got {:?}", value));
+    }
+    let mut result = String::new();
+    let mut chars = value[1..value.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            // Quotes within are escaped, this one ends the string early
+            return Err(format!("Unexpected quote in {:?}", value));
+        }
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some(c @ '\\') | Some(c @ '"') | Some(c @ '\'') => result.push(c),
+            other => return Err(format!("Unsupported escape {:?} in {:?}", other, value)),
+        }
+    }
+    Ok(result)
+}
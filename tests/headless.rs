@@ -0,0 +1,1009 @@
+//! Native test suite running applications without a DOM.
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use wand::core::{Application, CanvasMeta};
+use wand::component::{Event, EventKind, EventPhase, EventType, MouseButton, Modifiers};
+use wand::canvas::{Canvas2D, NullCanvas, RecordingCanvas, RasterCanvas, DrawCommand};
+use wand::transition::{Transition, TransitionKind};
+use wand::layer::{self, Layer, Surface};
+use wand::replay::{InputLog, InputPlayer};
+use wand::drag::DragPayload;
+use wand::testing;
+use wand::prelude::renderer::RendererContext;
+use wand::container::Scrollable;
+use wand::{Container, FrameTime, SpanTrait, TextSpan};
+
+#[derive(Default)]
+struct Probe {
+    ticks: u32,
+    renders: u32,
+    events: u32,
+    kinds: Vec<EventType>,
+    last_event: Option<Event>,
+    elapsed: f64,
+    rect: (f64, f64, f64, f64),
+}
+
+struct ProbeSpan {
+    name: String,
+    probe: Rc<RefCell<Probe>>,
+}
+
+impl SpanTrait for ProbeSpan {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn dispatch_event(&mut self, ev: &mut Event) {
+        let mut probe = self.probe.borrow_mut();
+        probe.events += 1;
+        probe.kinds.push(ev.ev);
+        probe.last_event = Some(ev.clone());
+    }
+
+    fn tick(&mut self, time: &FrameTime) {
+        let mut probe = self.probe.borrow_mut();
+        probe.ticks += 1;
+        probe.elapsed = time.elapsed;
+    }
+
+    fn render_tick(&self, _ctx: &RendererContext) {
+        self.probe.borrow_mut().renders += 1;
+    }
+
+    fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool) {
+        self.probe.borrow_mut().rect = (left, top, right, bottom);
+        (right - left, bottom - top, true)
+    }
+}
+
+struct OverlaySpan;
+
+impl SpanTrait for OverlaySpan {
+    fn get_name(&self) -> &str {
+        "overlay"
+    }
+
+    fn dispatch_event(&mut self, _ev: &mut Event) {}
+
+    fn render_tick(&self, ctx: &RendererContext) {
+        ctx.context_2d.fill_text("overlay", 0., 0.);
+    }
+
+    fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool) {
+        (right - left, bottom - top, true)
+    }
+
+    fn get_layer(&self) -> &str {
+        "overlay"
+    }
+}
+
+// Swallows every event it receives
+struct StopSpan {
+    hits: Rc<RefCell<u32>>,
+}
+
+impl SpanTrait for StopSpan {
+    fn get_name(&self) -> &str {
+        "stop"
+    }
+
+    fn dispatch_event(&mut self, ev: &mut Event) {
+        *self.hits.borrow_mut() += 1;
+        ev.stop_propagation();
+        ev.prevent_default();
+    }
+
+    fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool) {
+        (right - left, bottom - top, true)
+    }
+}
+
+// Focusable span stopping the propagation of the keys it receives when `consume` is set
+struct FocusSpan {
+    name: String,
+    consume: bool,
+    probe: Rc<RefCell<Probe>>,
+}
+
+impl SpanTrait for FocusSpan {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn dispatch_event(&mut self, ev: &mut Event) {
+        self.probe.borrow_mut().kinds.push(ev.ev);
+        if self.consume && ev.key.is_some() {
+            ev.stop_propagation();
+        }
+    }
+
+    fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool) {
+        self.probe.borrow_mut().rect = (left, top, right, bottom);
+        (right - left, bottom - top, true)
+    }
+
+    fn get_rect(&self) -> Option<wand::component::Rect> {
+        let (left, top, right, bottom) = self.probe.borrow().rect;
+        Some(wand::component::Rect::new(left, top, right - left, bottom - top))
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+}
+
+// Span covering its container, drawn over the spans of lower order
+struct Marker {
+    name: &'static str,
+    order: u8,
+}
+
+impl SpanTrait for Marker {
+    fn get_name(&self) -> &str {
+        self.name
+    }
+
+    fn dispatch_event(&mut self, _ev: &mut Event) {}
+
+    fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool) {
+        (right - left, bottom - top, true)
+    }
+
+    fn get_order(&self) -> u8 {
+        self.order
+    }
+}
+
+// Card of 40x20 at the start of its container, optionally draggable
+struct Card {
+    name: &'static str,
+    draggable: bool,
+    rect: wand::component::Rect,
+}
+
+impl SpanTrait for Card {
+    fn get_name(&self) -> &str {
+        self.name
+    }
+
+    fn render_tick(&self, ctx: &RendererContext) {
+        ctx.context_2d.fill_rect(self.rect.x, self.rect.y, self.rect.w, self.rect.h);
+    }
+
+    fn on_resize(&mut self, left: f64, top: f64, _right: f64, _bottom: f64) -> (f64, f64, bool) {
+        self.rect = wand::component::Rect::new(left, top, 40., 20.);
+        (40., 20., true)
+    }
+
+    fn get_rect(&self) -> Option<wand::component::Rect> {
+        Some(self.rect)
+    }
+
+    fn drag_payload(&self) -> Option<DragPayload> {
+        Some(DragPayload::new("card", self.name.to_string())).filter(|_| self.draggable)
+    }
+}
+
+fn flat_container() -> Container {
+    Container::new(0., 0., 0., 0., 0., 0., Scrollable::None)
+}
+
+// Headless application with a "main" scene filled by `build`, which returns what the test needs of it
+fn build_app_with<T, F>(meta: CanvasMeta, canvas: Rc<dyn Canvas2D>, build: F) -> (Application, T)
+    where F: FnOnce(&mut wand::Scene, &mut Application) -> T
+{
+    let mut app = Application::new_headless(meta, canvas);
+    let mut scene = wand::Scene::new(app.get_state(), "main", 0., 0., 0., 0., 0., 0., flat_container());
+    let built = build(&mut scene, &mut app);
+    app.register(scene);
+    (app, built)
+}
+
+fn build_app(probe: &Rc<RefCell<Probe>>) -> Application {
+    let (mut app, _) = build_app_with(CanvasMeta::new(800, 600), Rc::new(NullCanvas::new()), |scene, app| {
+        let section = app.new_section_with_container("section", 1., 1., flat_container());
+        {
+            let mut section = section.borrow_mut();
+            section.register_span(ProbeSpan { name: "probe".to_string(), probe: probe.clone() });
+            section.register_span(TextSpan::new(app.get_state(), "label", "Label", 1., 1.));
+        }
+        scene.register_section(&section);
+    });
+    app.on_resize();
+    app
+}
+
+#[test]
+fn headless_application_runs() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+
+    app.on_mouse_move(400., 300.);
+    // Ticking renders the frame too
+    app.tick();
+    assert_eq!(probe.borrow().renders, 1);
+    // Nothing was invalidated since, so there is nothing left to draw
+    app.render_tick();
+
+    let probe = probe.borrow();
+    assert_eq!(probe.events, 1);
+    assert_eq!(probe.ticks, 1);
+    assert_eq!(probe.renders, 1);
+    assert_eq!(probe.rect, (0., 0., 800., 600.));
+}
+
+#[test]
+fn headless_application_resizes() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+    app.set_size(400, 200);
+    assert_eq!(app.get_meta().w, 400);
+    assert_eq!(probe.borrow().rect, (0., 0., 400., 200.));
+    assert!(app.get_state().borrow().fetch_span("label").is_some());
+}
+
+#[test]
+fn golden_layout_frame() {
+    let canvas = Rc::new(RecordingCanvas::new());
+    let (mut app, _) = build_app_with(CanvasMeta::new(400, 300), canvas.clone(), |scene, app| {
+        let left = app.new_section_with_container("left", 0.5, 1., flat_container());
+        let right = app.new_section_with_container("right", 0.5, 1., flat_container());
+        left.borrow_mut().register_span(TextSpan::new(app.get_state(), "title", "Title", 1., 0.5));
+        right.borrow_mut().register_span(TextSpan::new(app.get_state(), "body", "Body text", 1., 1.));
+        scene.register_section(&left);
+        scene.register_section(&right);
+    });
+    app.on_resize();
+
+    let frame = testing::record_frame(&app, &canvas);
+    testing::assert_golden(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/layout.txt"), &frame);
+}
+
+#[test]
+fn golden_raster_frame() {
+    let canvas = Rc::new(RasterCanvas::new(200, 100));
+    let (mut app, _) = build_app_with(CanvasMeta::new(200, 100), canvas.clone(), |scene, app| {
+        let section = app.new_section("section", 1., 1., 0.1);
+        section.borrow_mut().register_span(TextSpan::new(app.get_state(), "hello", "Hello 42", 1., 1.));
+        scene.register_section(&section);
+    });
+    app.on_resize();
+
+    let png = testing::raster_frame(&app, &canvas);
+    assert_eq!(canvas.pixel(0, 0), [0x07, 0xce, 0x88, 255]);
+    assert_eq!(canvas.pixel(100, 2), [0, 0, 0, 0]);
+    testing::assert_golden_png(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/raster.png"), &png);
+}
+
+#[test]
+fn missing_golden_files_fail_unless_blessing() {
+    if std::env::var("WAND_BLESS").map(|v| v == "1").unwrap_or(false) {
+        return;
+    }
+    let path = std::env::temp_dir().join("wand-missing-golden.txt");
+    let _ = std::fs::remove_file(&path);
+    let result = std::panic::catch_unwind(|| testing::assert_golden(&path, &wand::canvas::Frame::default()));
+    let message = result.unwrap_err();
+    assert!(message.downcast_ref::<String>().unwrap().starts_with("Missing golden file"));
+    assert!(!path.exists());
+}
+
+#[test]
+fn scene_navigation_history() {
+    let mut app = Application::new_headless(CanvasMeta::new(300, 200), Rc::new(NullCanvas::new()));
+    let state = app.get_state();
+    for path in ["home", "settings", "about"].iter() {
+        assert!(app.register(wand::Scene::new(state.clone(), path, 0., 0., 0., 0., 0., 0., flat_container())));
+    }
+    assert!(!app.register(wand::Scene::new(state.clone(), "home", 0., 0., 0., 0., 0., 0., flat_container())));
+    assert_eq!(app.get_path(), "home");
+    assert_eq!(app.get_scene("settings").unwrap().w, 0.);
+
+    let changes = Rc::new(RefCell::new(Vec::new()));
+    {
+        let changes = changes.clone();
+        app.set_on_scene_change(move |from, to| changes.borrow_mut().push(format!("{}>{}", from, to)));
+    }
+
+    assert!(app.navigate("settings"));
+    assert_eq!(app.get_scene("settings").unwrap().w, 300.);
+    assert!(app.navigate("about"));
+    assert!(!app.navigate("missing"));
+    assert!(app.back());
+    assert_eq!(app.get_path(), "settings");
+    assert!(app.back());
+    assert!(!app.back());
+    assert!(app.forward());
+    assert!(app.navigate("about"));
+    assert!(!app.can_go_forward());
+    assert_eq!(*changes.borrow(), vec!["home>settings", "settings>about", "about>settings", "settings>home", "home>settings", "settings>about"]);
+}
+
+#[test]
+fn scene_transition_draws_both_scenes() {
+    let canvas = Rc::new(RecordingCanvas::new());
+    let mut app = Application::new_headless(CanvasMeta::new(300, 200), canvas.clone());
+    let state = app.get_state();
+    app.register(wand::Scene::new(state.clone(), "home", 0., 0., 0., 0., 0., 0., flat_container()));
+    app.register(wand::Scene::new(state.clone(), "next", 0., 0., 0., 0., 0., 0., flat_container()));
+    app.set_transition(Transition::new(TransitionKind::SlideLeft, 100.));
+
+    assert!(app.navigate("next"));
+    assert!(app.is_transitioning());
+    app.advance_transition(50.);
+    let frame = testing::record_frame(&app, &canvas);
+    let translations: Vec<&DrawCommand> = frame.commands.iter()
+        .filter(|c| if let DrawCommand::Translate { .. } = c { true } else { false })
+        .collect();
+    assert_eq!(translations, vec![
+        &DrawCommand::Translate { x: -150., y: 0. },
+        &DrawCommand::Translate { x: 150., y: 0. },
+    ]);
+
+    app.advance_transition(50.);
+    assert!(!app.is_transitioning());
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.iter().all(|c| if let DrawCommand::Translate { .. } = c { false } else { true }));
+}
+
+#[test]
+fn fixed_timestep_ticks() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+
+    app.tick_with(16.);
+    assert_eq!(app.get_frame_time(), FrameTime { dt: 16., elapsed: 16., frame: 0, fixed: false });
+
+    app.set_fixed_timestep(Some(10.));
+    app.tick_with(25.);
+    assert_eq!(probe.borrow().ticks, 3);
+    app.tick_with(5.);
+    assert_eq!(probe.borrow().ticks, 4);
+    assert_eq!(probe.borrow().elapsed, 46.);
+    assert_eq!(app.get_frame_time(), FrameTime { dt: 10., elapsed: 46., frame: 3, fixed: true });
+}
+
+struct PressSpan {
+    input: wand::input::Input,
+    presses: Rc<RefCell<Vec<u32>>>,
+}
+
+impl SpanTrait for PressSpan {
+    fn get_name(&self) -> &str {
+        "press"
+    }
+
+    fn dispatch_event(&mut self, _ev: &mut Event) {}
+
+    fn tick(&mut self, _time: &FrameTime) {
+        let pressed = self.input.borrow().just_pressed("x") as u32;
+        self.presses.borrow_mut().push(pressed);
+    }
+
+    fn render_tick(&self, _ctx: &RendererContext) {}
+
+    fn on_resize(&mut self, left: f64, top: f64, right: f64, bottom: f64) -> (f64, f64, bool) {
+        (right - left, bottom - top, true)
+    }
+}
+
+#[test]
+fn fixed_timestep_steps_see_each_press_once() {
+    let presses = Rc::new(RefCell::new(Vec::new()));
+    let (mut app, _) = build_app_with(CanvasMeta::new(200, 100), Rc::new(NullCanvas::new()), |scene, app| {
+        let section = app.new_section_with_container("main", 1., 1., flat_container());
+        section.borrow_mut().register_span(PressSpan { input: app.input.clone(), presses: presses.clone() });
+        scene.register_section(&section);
+    });
+    app.set_fixed_timestep(Some(16.));
+
+    // No step runs this frame, the press waits for the next one
+    app.on_keydown("x");
+    app.tick_with(8.);
+    assert!(presses.borrow().is_empty());
+    app.tick_with(8.);
+    assert_eq!(*presses.borrow(), vec![1]);
+
+    // Several steps in one frame see the press in the first step only
+    app.on_keyup("x");
+    app.tick_with(16.);
+    app.on_keydown("x");
+    app.tick_with(48.);
+    assert_eq!(*presses.borrow(), vec![1, 0, 1, 0, 0]);
+    assert_eq!(app.input.borrow().hold_duration("x"), 32.);
+}
+
+#[test]
+fn dirty_regions_redraw_invalidated_spans_only() {
+    let canvas = Rc::new(RecordingCanvas::new());
+    let (mut app, _) = build_app_with(CanvasMeta::new(400, 300), canvas.clone(), |scene, app| {
+        let left = app.new_section_with_container("left", 0.5, 1., flat_container());
+        let right = app.new_section_with_container("right", 1., 1., flat_container());
+        left.borrow_mut().register_span(TextSpan::new(app.get_state(), "static", "Static", 1., 1.));
+        right.borrow_mut().register_span(TextSpan::new(app.get_state(), "status", "Idle", 1., 1.));
+        scene.register_section(&left);
+        scene.register_section(&right);
+    });
+    let state = app.get_state();
+
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.contains(&DrawCommand::ClearRect { x: 0., y: 0., w: 400., h: 300. }));
+    assert!(testing::record_frame(&app, &canvas).commands.is_empty());
+
+    let status = state.borrow().fetch_span("status").unwrap();
+    {
+        // Spans invalidate without going through the state, callers may hold it
+        let _state = state.borrow_mut();
+        status.borrow_mut().dispatch(Box::new("Busy".to_string()));
+    }
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.contains(&DrawCommand::ClearRect { x: 199., y: -1., w: 202., h: 302. }));
+    let texts: Vec<&str> = frame.commands.iter().filter_map(|c| match c {
+        DrawCommand::FillText { text, .. } => Some(text.as_str()),
+        _ => None,
+    }).collect();
+    assert!(texts.contains(&"Busy"));
+    assert!(frame.commands.contains(&DrawCommand::Clip));
+    assert!(!frame.commands.contains(&DrawCommand::ClearRect { x: 0., y: 0., w: 400., h: 300. }));
+
+    app.set_size(400, 200);
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.contains(&DrawCommand::ClearRect { x: 0., y: 0., w: 400., h: 200. }));
+}
+
+#[test]
+fn cached_section_redraws_only_when_invalidated() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let canvas = Rc::new(RecordingCanvas::new());
+    let (mut app, toolbar) = build_app_with(CanvasMeta::new(400, 300), canvas.clone(), |scene, app| {
+        let toolbar = app.new_section_with_container("toolbar", 1., 1., flat_container());
+        {
+            let mut toolbar = toolbar.borrow_mut();
+            toolbar.set_cached(true);
+            toolbar.register_span(ProbeSpan { name: "probe".to_string(), probe: probe.clone() });
+        }
+        scene.register_section(&toolbar);
+        toolbar
+    });
+
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.contains(&DrawCommand::DrawLayer { x: -1., y: -1., w: 402., h: 302. }));
+    assert_eq!(probe.borrow().renders, 1);
+
+    app.invalidate();
+    testing::record_frame(&app, &canvas);
+    assert_eq!(probe.borrow().renders, 1);
+
+    toolbar.borrow().invalidate();
+    testing::record_frame(&app, &canvas);
+    assert_eq!(probe.borrow().renders, 2);
+
+    app.set_size(200, 100);
+    testing::record_frame(&app, &canvas);
+    assert_eq!(probe.borrow().renders, 3);
+}
+
+#[test]
+fn hidpi_layout_uses_logical_pixels() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let canvas = Rc::new(RecordingCanvas::new());
+    let (mut app, _) = build_app_with(CanvasMeta::with_ratio(200, 100, 2.), canvas.clone(), |scene, app| {
+        let section = app.new_section_with_container("section", 1., 1., flat_container());
+        section.borrow_mut().register_span(ProbeSpan { name: "probe".to_string(), probe: probe.clone() });
+        scene.register_section(&section);
+    });
+
+    assert_eq!((app.get_meta().device_width(), app.get_meta().device_height()), (400, 200));
+    assert_eq!(probe.borrow().rect, (0., 0., 200., 100.));
+    let frame = testing::record_frame(&app, &canvas);
+    assert_eq!(frame.commands[..2], [DrawCommand::Save, DrawCommand::Scale { x: 2., y: 2. }]);
+    assert!(frame.commands.contains(&DrawCommand::ClearRect { x: 0., y: 0., w: 200., h: 100. }));
+
+    app.set_pixel_ratio(1.);
+    assert_eq!(app.get_meta().device_width(), 200);
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(!frame.commands.contains(&DrawCommand::Scale { x: 2., y: 2. }));
+}
+
+#[test]
+fn spans_render_to_their_scene_layer() {
+    let canvas = Rc::new(RecordingCanvas::new());
+    let overlay = Rc::new(RecordingCanvas::new());
+    let (app, _) = build_app_with(CanvasMeta::new(200, 100), canvas.clone(), |scene, app| {
+        app.set_surface("overlay", Surface::new_2d(overlay.clone()));
+        scene.set_layers(vec![
+            Layer::canvas(layer::DEFAULT_LAYER),
+            Layer::webgl(layer::WORLD_LAYER),
+            Layer::canvas("overlay"),
+        ]);
+        let section = app.new_section_with_container("section", 1., 1., flat_container());
+        {
+            let mut section = section.borrow_mut();
+            section.register_span(TextSpan::new(app.get_state(), "label", "Label", 1., 0.5));
+            section.register_span(OverlaySpan);
+        }
+        scene.register_section(&section);
+    });
+    assert!(app.get_surface(layer::WORLD_LAYER).is_none());
+
+    let frame = testing::record_frame(&app, &canvas);
+    let overlay_frame = overlay.take_frame();
+    let texts = |frame: &wand::canvas::Frame| -> Vec<String> {
+        frame.commands.iter().filter_map(|c| match c {
+            DrawCommand::FillText { text, .. } => Some(text.clone()),
+            _ => None,
+        }).collect()
+    };
+    assert_eq!(texts(&frame), vec!["Label".to_string()]);
+    assert_eq!(texts(&overlay_frame), vec!["overlay".to_string()]);
+    assert!(overlay_frame.commands.contains(&DrawCommand::ClearRect { x: 0., y: 0., w: 200., h: 100. }));
+    assert!(!overlay_frame.commands.contains(&DrawCommand::StrokeRect { x: 0., y: 0., w: 200., h: 100. }));
+}
+
+#[test]
+fn layers_left_behind_by_a_scene_are_cleared() {
+    let canvas = Rc::new(RecordingCanvas::new());
+    let overlay = Rc::new(RecordingCanvas::new());
+    let mut app = Application::new_headless(CanvasMeta::new(200, 100), canvas.clone());
+    app.set_surface("overlay", Surface::new_2d(overlay.clone()));
+    let state = app.get_state();
+    let mut game = wand::Scene::new(state.clone(), "game", 0., 0., 0., 0., 0., 0., flat_container());
+    game.set_layers(vec![Layer::canvas(layer::DEFAULT_LAYER), Layer::canvas("overlay")]);
+    let section = app.new_section_with_container("section", 1., 1., flat_container());
+    section.borrow_mut().register_span(OverlaySpan);
+    game.register_section(&section);
+    app.register(game);
+    app.register(wand::Scene::new(state.clone(), "menu", 0., 0., 0., 0., 0., 0., flat_container()));
+    app.on_resize();
+
+    testing::record_frame(&app, &canvas);
+    assert!(overlay.take_frame().commands.contains(&DrawCommand::FillText { text: "overlay".to_string(), x: 0., y: 0. }));
+
+    assert!(app.navigate("menu"));
+    testing::record_frame(&app, &canvas);
+    let frame = overlay.take_frame();
+    assert_eq!(frame.commands, vec![DrawCommand::ClearRect { x: 0., y: 0., w: 200., h: 100. }]);
+    // Later frames draw nothing there anymore
+    app.invalidate();
+    testing::record_frame(&app, &canvas);
+    assert!(overlay.take_frame().commands.iter().all(|c| match c {
+        DrawCommand::ClearRect { .. } => true,
+        _ => false,
+    }));
+}
+
+#[test]
+fn pointer_events_carry_buttons_modifiers_and_deltas() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+    let last = || probe.borrow().last_event.clone().unwrap();
+
+    app.on_mouse_down(10., 20., MouseButton::Primary, Modifiers::from_bits(Modifiers::SHIFT));
+    let ev = last();
+    assert_eq!((ev.ev, ev.button, ev.pos.x(), ev.pos.y()), (EventType::MouseDown, Some(MouseButton::Primary), 10., 20.));
+    assert!(ev.modifiers.shift && !ev.modifiers.ctrl);
+
+    app.on_mouse_up(10., 20., MouseButton::Primary, Modifiers::default());
+    assert_eq!(last().ev, EventType::MouseUp);
+    app.on_click(10., 20., MouseButton::Primary, Modifiers::default());
+    assert_eq!(last().ev, EventType::Click);
+    app.on_double_click(10., 20., MouseButton::Primary, Modifiers::default());
+    assert_eq!(last().ev, EventType::DoubleClick);
+
+    app.on_context_menu(10., 20., Modifiers::from_bits(Modifiers::CTRL | Modifiers::META));
+    let ev = last();
+    assert_eq!((ev.ev, ev.button), (EventType::ContextMenu, Some(MouseButton::Secondary)));
+    assert_eq!(ev.modifiers.bits(), Modifiers::CTRL | Modifiers::META);
+
+    app.on_wheel(10., 20., 0., -120., Modifiers::default());
+    let ev = last();
+    assert_eq!((ev.ev, ev.delta_x, ev.delta_y), (EventType::Wheel, 0., -120.));
+    assert_eq!(probe.borrow().events, 6);
+}
+
+#[test]
+fn stopped_events_skip_siblings_below() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+    let ev = app.dispatch_event(Event::new(EventType::Click, 100., 100.));
+    assert_eq!(probe.borrow().last_event.as_ref().unwrap().phase, EventPhase::Target);
+    assert!(!ev.consumed && !ev.default_prevented);
+    assert_eq!(ev.phase, EventPhase::None);
+
+    let hits = Rc::new(RefCell::new(0));
+    let section = app.get_state().borrow().fetch_section("section").unwrap();
+    section.borrow_mut().register_span(StopSpan { hits: hits.clone() });
+    app.on_resize();
+
+    let ev = app.dispatch_event(Event::new(EventType::Click, 100., 100.));
+    assert!(ev.consumed && ev.default_prevented);
+    assert_eq!(*hits.borrow(), 1);
+    assert_eq!(probe.borrow().events, 1);
+}
+
+#[test]
+fn hover_enters_and_leaves_items_under_the_pointer() {
+    let (a, b) = (Rc::new(RefCell::new(Probe::default())), Rc::new(RefCell::new(Probe::default())));
+    let (mut app, (left, right)) = build_app_with(CanvasMeta::new(200, 100), Rc::new(NullCanvas::new()), |scene, app| {
+        let left = app.new_section_with_container("left", 0.5, 1., flat_container());
+        let right = app.new_section_with_container("right", 1., 1., flat_container());
+        left.borrow_mut().register_span(ProbeSpan { name: "a".to_string(), probe: a.clone() });
+        right.borrow_mut().register_span(ProbeSpan { name: "b".to_string(), probe: b.clone() });
+        scene.register_section(&left);
+        scene.register_section(&right);
+        (left, right)
+    });
+
+    app.on_mouse_move(50., 50.);
+    assert_eq!(a.borrow().kinds, vec![EventType::PointerEnter, EventType::MouseMove]);
+    assert!(left.borrow().is_hovered() && app.is_hovered("a"));
+    app.on_mouse_move(60., 50.);
+    assert_eq!(a.borrow().kinds.len(), 3);
+
+    app.on_mouse_move(150., 50.);
+    assert_eq!(a.borrow().kinds[3], EventType::PointerLeave);
+    assert_eq!(b.borrow().kinds, vec![EventType::PointerEnter, EventType::MouseMove]);
+    assert!(!left.borrow().is_hovered() && right.borrow().is_hovered());
+    assert!(!app.is_hovered("a") && app.is_hovered("right"));
+
+    app.on_pointer_leave(199., 50.);
+    assert_eq!(b.borrow().kinds.last(), Some(&EventType::PointerLeave));
+    assert!(!right.borrow().is_hovered() && !app.is_hovered("b"));
+}
+
+#[test]
+fn focus_moves_with_tab_and_clicks_and_routes_keys() {
+    let (a, b) = (Rc::new(RefCell::new(Probe::default())), Rc::new(RefCell::new(Probe::default())));
+    let (mut app, _) = build_app_with(CanvasMeta::new(200, 100), Rc::new(NullCanvas::new()), |scene, app| {
+        let left = app.new_section_with_container("left", 0.5, 1., flat_container());
+        let right = app.new_section_with_container("right", 1., 1., flat_container());
+        left.borrow_mut().register_span(FocusSpan { name: "a".to_string(), consume: true, probe: a.clone() });
+        right.borrow_mut().register_span(FocusSpan { name: "b".to_string(), consume: false, probe: b.clone() });
+        scene.register_section(&left);
+        scene.register_section(&right);
+    });
+    assert_eq!(app.get_focus(), None);
+
+    app.on_keydown("Tab");
+    assert_eq!(app.get_focus(), Some("a".to_string()));
+    assert!(app.on_keydown_with("x", Modifiers::default()).consumed);
+    assert!(!app.input.borrow().keydown("x"));
+
+    app.on_keydown("Tab");
+    assert_eq!(app.get_focus(), Some("b".to_string()));
+    assert_eq!(a.borrow().kinds, vec![EventType::Focus, EventType::KeyDown, EventType::KeyDown, EventType::Blur]);
+    assert!(!app.on_keydown_with("y", Modifiers::default()).consumed);
+    // Keys reach the input snapshot on the next tick
+    assert!(!app.input.borrow().is_down("y"));
+    app.tick_with(16.);
+    assert!(app.input.borrow().just_pressed("y"));
+    assert!(!app.input.borrow().is_down("x"));
+    assert_eq!(b.borrow().kinds, vec![EventType::Focus, EventType::KeyDown]);
+
+    app.on_keydown_with("Tab", Modifiers::from_bits(Modifiers::SHIFT));
+    assert_eq!(app.get_focus(), Some("a".to_string()));
+
+    app.on_mouse_down(150., 50., MouseButton::Primary, Modifiers::default());
+    assert_eq!(app.get_focus(), Some("b".to_string()));
+    assert!(app.focus("a"));
+    assert!(!app.focus("missing"));
+    app.blur();
+    assert_eq!(app.get_focus(), None);
+}
+
+#[test]
+fn hit_test_follows_order_visibility_and_section_areas() {
+    let (app, inner) = build_app_with(CanvasMeta::new(200, 100), Rc::new(NullCanvas::new()), |scene, app| {
+        let outer = app.new_section_with_container("outer", 0.5, 1., flat_container());
+        let inner = app.new_section_with_container("inner", 1., 0.5, flat_container());
+        {
+            let mut inner = inner.borrow_mut();
+            inner.register_span(Marker { name: "above", order: 1 });
+            inner.register_span(Marker { name: "below", order: 0 });
+        }
+        outer.borrow_mut().register_section(&inner);
+        scene.register_section(&outer);
+        inner
+    });
+
+    assert_eq!(app.hit_test(50., 25.), vec!["outer", "inner", "above"]);
+    assert_eq!(app.hit_test(50., 75.), vec!["outer"]);
+    assert!(app.hit_test(150., 25.).is_empty());
+
+    inner.borrow_mut().set_visible(false);
+    assert_eq!(app.hit_test(50., 25.), vec!["outer"]);
+}
+
+#[test]
+fn listeners_registered_through_state_run_until_removed() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+    let state = app.get_state();
+    let phases = Rc::new(RefCell::new(Vec::new()));
+
+    let log = phases.clone();
+    let on_section = state.borrow().on("section", EventKind::Click, move |ev| log.borrow_mut().push(("section", ev.phase)));
+    let log = phases.clone();
+    state.borrow().on_capture("section", EventKind::Click, move |ev| log.borrow_mut().push(("capture", ev.phase)));
+    let weak = Rc::downgrade(&state);
+    let log = phases.clone();
+    state.borrow().on("label", EventKind::Click, move |ev| {
+        log.borrow_mut().push(("label", ev.phase));
+        // Listeners may update the span they listen on
+        let label = weak.upgrade().unwrap().borrow().fetch_span("label").unwrap();
+        label.borrow_mut().dispatch(Box::new("Clicked".to_string()));
+    });
+    state.borrow().on("label", EventKind::MouseMove, |_| panic!("not listening to moves"));
+    app.on_click(100., 100., MouseButton::Primary, Modifiers::default());
+    assert_eq!(*phases.borrow(), vec![
+        ("capture", EventPhase::Capture),
+        ("label", EventPhase::Target),
+        ("section", EventPhase::Bubble),
+    ]);
+
+    assert!(state.borrow().off(&on_section));
+    assert!(!state.borrow().off(&on_section));
+    phases.borrow_mut().clear();
+    app.on_click(100., 100., MouseButton::Primary, Modifiers::default());
+    assert_eq!(phases.borrow().len(), 2);
+}
+
+#[test]
+fn section_listeners_may_update_the_sections_on_the_route() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+    let state = app.get_state();
+    let weak = Rc::downgrade(&state);
+    state.borrow().on_capture("section", EventKind::Click, move |_| {
+        let section = weak.upgrade().unwrap().borrow().fetch_section("section").unwrap();
+        assert!(section.borrow().is_visible());
+    });
+    let weak = Rc::downgrade(&state);
+    state.borrow().on("section", EventKind::Click, move |_| {
+        // Toggle the section the listener is on, while the click is still bubbling
+        let section = weak.upgrade().unwrap().borrow().fetch_section("section").unwrap();
+        let visible = section.borrow().is_visible();
+        section.borrow_mut().set_visible(!visible);
+        section.borrow().invalidate();
+    });
+    let weak = Rc::downgrade(&state);
+    state.borrow().on("label", EventKind::Click, move |_| {
+        let section = weak.upgrade().unwrap().borrow().fetch_section("section").unwrap();
+        let label = section.borrow_mut().take_item("label").unwrap();
+        section.borrow_mut().insert_item(label);
+    });
+
+    assert!(app.hit_test(100., 100.).contains(&"section".to_string()));
+    app.on_click(100., 100., MouseButton::Primary, Modifiers::default());
+    let section = state.borrow().fetch_section("section").unwrap();
+    assert!(!section.borrow().is_visible());
+    assert!(app.hit_test(100., 100.).is_empty());
+}
+
+#[test]
+fn recorded_input_replays_into_the_same_state() {
+    let original = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&original);
+    app.start_recording();
+    app.set_size(400, 200);
+    app.on_mouse_move(100., 50.);
+    app.on_mouse_down(100., 50., MouseButton::Secondary, Modifiers::from_bits(Modifiers::CTRL));
+    app.on_wheel(100., 50., 0., -120., Modifiers::default());
+    app.on_keydown("Enter");
+    app.tick_with(16.);
+    app.on_pointer_leave(100., 50.);
+    let log = app.stop_recording().unwrap();
+    assert!(!app.is_recording());
+    assert_eq!(log.records.len(), 7);
+
+    // Replay from the serialized form, as a log saved to a file would be
+    let log = InputLog::parse(&log.to_string()).unwrap();
+    let replayed = Rc::new(RefCell::new(Probe::default()));
+    let mut copy = build_app(&replayed);
+    let mut player = InputPlayer::new(log);
+    player.play_all(&mut copy);
+    assert!(player.is_done());
+
+    let (original, replayed) = (original.borrow(), replayed.borrow());
+    assert_eq!(replayed.kinds, original.kinds);
+    assert_eq!(replayed.last_event.as_ref().map(|ev| ev.modifiers), original.last_event.as_ref().map(|ev| ev.modifiers));
+    assert_eq!((replayed.ticks, replayed.elapsed, replayed.rect), (original.ticks, original.elapsed, original.rect));
+    assert_eq!(copy.get_meta().w, 400);
+    assert!(copy.input.borrow().is_down("Enter"));
+}
+
+#[test]
+fn cards_drag_between_sections_accepting_them() {
+    let canvas = Rc::new(RecordingCanvas::new());
+    let (mut app, (todo, done)) = build_app_with(CanvasMeta::new(200, 100), canvas.clone(), |scene, app| {
+        let todo = app.new_section_with_container("todo", 0.5, 1., flat_container());
+        let done = app.new_section_with_container("done", 1., 1., flat_container());
+        todo.borrow_mut().register_span(Card { name: "card", draggable: true, rect: Default::default() });
+        done.borrow_mut().set_drop_target(|payload| payload.kind() == "card");
+        scene.register_section(&todo);
+        scene.register_section(&done);
+        (todo, done)
+    });
+    let state = app.get_state();
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let kinds = [EventKind::DragStart, EventKind::DragEnter, EventKind::DragOver, EventKind::DragLeave, EventKind::Drop, EventKind::DragEnd];
+    for name in ["card", "done"].iter() {
+        for kind in kinds.iter() {
+            let log = log.clone();
+            state.borrow().on(name, *kind, move |ev| log.borrow_mut().push((*name, ev.ev)));
+        }
+    }
+    let (from, to) = (todo.clone(), done.clone());
+    state.borrow().on("done", EventKind::Drop, move |ev| {
+        let name = ev.payload.as_ref().and_then(|payload| payload.get::<String>()).unwrap();
+        let card = from.borrow_mut().take_item(name).unwrap();
+        to.borrow_mut().insert_item(card);
+    });
+
+    app.on_mouse_down(20., 10., MouseButton::Primary, Modifiers::default());
+    app.on_mouse_move(22., 10.);
+    assert!(!app.is_dragging());
+    app.on_mouse_move(150., 50.);
+    assert!(app.is_dragging());
+    assert_eq!(app.get_drop_target(), Some("done".to_string()));
+
+    // The ghost is a translucent copy of the card following the pointer
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.contains(&DrawCommand::GlobalAlpha(wand::drag::GHOST_ALPHA)));
+    assert!(frame.commands.contains(&DrawCommand::Translate { x: 130., y: 40. }));
+    assert!(frame.commands.contains(&DrawCommand::StrokeRect { x: 130., y: 40., w: 40., h: 20. }));
+
+    app.on_mouse_move(50., 50.);
+    assert_eq!(app.get_drop_target(), None);
+    app.on_mouse_move(150., 50.);
+    app.on_mouse_up(150., 50., MouseButton::Primary, Modifiers::default());
+    assert!(!app.is_dragging());
+    assert_eq!(*log.borrow(), vec![
+        ("card", EventType::DragStart),
+        ("done", EventType::DragEnter),
+        ("done", EventType::DragOver),
+        ("done", EventType::DragLeave),
+        ("done", EventType::DragEnter),
+        ("done", EventType::DragOver),
+        ("done", EventType::Drop),
+        ("card", EventType::DragEnd),
+    ]);
+    assert_eq!(app.hit_test(120., 10.), vec!["done", "card"]);
+    assert_eq!(app.hit_test(20., 10.), vec!["todo"]);
+}
+
+#[test]
+fn touches_are_tracked_per_pointer_and_make_gestures() {
+    let probe = Rc::new(RefCell::new(Probe::default()));
+    let mut app = build_app(&probe);
+    let last = || probe.borrow().last_event.clone().unwrap();
+
+    app.on_touch_start(7, 100., 100.);
+    assert_eq!(last().pointer_id, Some(7));
+    app.tick_with(50.);
+    app.on_touch_end(7, 100., 100.);
+    assert_eq!(probe.borrow().kinds, vec![EventType::TouchStart, EventType::TouchEnd, EventType::Tap]);
+
+    probe.borrow_mut().kinds.clear();
+    app.on_touch_start(1, 100., 100.);
+    app.tick_with(600.);
+    assert_eq!(last().ev, EventType::LongPress);
+    app.on_touch_start(2, 200., 100.);
+    assert_eq!(app.get_touches().len(), 2);
+    app.on_touch_move(2, 300., 100.);
+    assert_eq!(last().ev, EventType::Pinch);
+    assert_eq!((last().scale, last().pos), (2., wand::component::Position::new(200., 100.)));
+    app.on_touch_cancel(2);
+    app.on_touch_end(1, 100., 100.);
+    assert!(app.get_touches().is_empty());
+    assert_eq!(probe.borrow().kinds, vec![
+        EventType::TouchStart,
+        EventType::LongPress,
+        EventType::TouchStart,
+        EventType::TouchMove,
+        EventType::Pinch,
+        EventType::TouchCancel,
+        EventType::TouchEnd,
+    ]);
+}
+
+#[test]
+fn scrolling_sections_clip_and_offset_their_items() {
+    let canvas = Rc::new(RecordingCanvas::new());
+    let (mut app, list) = build_app_with(CanvasMeta::new(200, 100), canvas.clone(), |scene, app| {
+        let list = Container::new(0., 0., 0., 0., 0., 0., Scrollable::Y);
+        let list = app.new_section_with_container("list", 0.5, 1., list);
+        for name in ["a", "b", "c", "d", "e", "f", "g", "h"].iter() {
+            list.borrow_mut().register_span(Card { name, draggable: false, rect: Default::default() });
+        }
+        scene.register_section(&list);
+        list
+    });
+    let state = app.get_state();
+    let scrolls = Rc::new(RefCell::new(Vec::new()));
+    let log = scrolls.clone();
+    state.borrow().on("list", EventKind::Scroll, move |ev| log.borrow_mut().push(ev.delta_y));
+
+    // Eight cards of 20 stacked in 100
+    assert_eq!(list.borrow().get_max_scroll(), (0., 60.));
+    assert_eq!(app.hit_test(20., 90.), vec!["list", "e"]);
+    app.on_wheel(20., 50., 0., 30., Modifiers::default());
+    assert_eq!(list.borrow().get_scroll(), (0., 30.));
+    assert_eq!(app.hit_test(20., 85.), vec!["list", "f"]);
+
+    // Dragging moves the items along with the pointer, up to the last one
+    app.on_mouse_down(20., 50., MouseButton::Primary, Modifiers::default());
+    app.on_mouse_move(20., 0.);
+    app.on_mouse_up(20., 0., MouseButton::Primary, Modifiers::default());
+    assert_eq!(list.borrow().get_scroll(), (0., 60.));
+    assert_eq!(*scrolls.borrow(), vec![30., 30.]);
+
+    // Scrolling leaves the layout alone, the items are drawn moved by the offset instead
+    // and clipped to the section, the ones out of view not drawn
+    let last = state.borrow().fetch_span("h").unwrap();
+    assert_eq!(last.borrow().get_rect(), Some(wand::component::Rect::new(0., 140., 40., 20.)));
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.contains(&DrawCommand::Clip));
+    assert!(frame.commands.contains(&DrawCommand::Translate { x: 0., y: -60. }));
+    assert!(frame.commands.contains(&DrawCommand::FillRect { x: 0., y: 140., w: 40., h: 20. }));
+    assert!(!frame.commands.contains(&DrawCommand::FillRect { x: 0., y: 0., w: 40., h: 20. }));
+
+    // Items invalidating where they are laid out are redrawn where they show
+    state.borrow().invalidate(wand::component::Rect::new(0., 140., 40., 20.));
+    let frame = testing::record_frame(&app, &canvas);
+    assert!(frame.commands.contains(&DrawCommand::ClearRect { x: -1., y: 79., w: 42., h: 22. }));
+    assert!(frame.commands.contains(&DrawCommand::FillRect { x: 0., y: 140., w: 40., h: 20. }));
+
+    assert!(app.scroll_into_view("b"));
+    assert_eq!(list.borrow().get_scroll(), (0., 20.));
+    assert!(!app.scroll_into_view("missing"));
+    list.borrow_mut().set_scroll(0., 500.);
+    assert_eq!(list.borrow().get_scroll(), (0., 60.));
+}
+
+#[test]
+fn kinetic_sections_fling_and_bounce_back() {
+    let (mut app, list) = build_app_with(CanvasMeta::new(200, 100), Rc::new(NullCanvas::new()), |scene, app| {
+        let list = Container::new(0., 0., 0., 0., 0., 0., Scrollable::Y);
+        let list = app.new_section_with_container("list", 0.5, 1., list);
+        for name in ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"].iter() {
+            list.borrow_mut().register_span(Card { name, draggable: false, rect: Default::default() });
+        }
+        list.borrow_mut().set_kinetic(Some(wand::scroll::ScrollPhysics::default()));
+        scene.register_section(&list);
+        list
+    });
+    let scroll = || list.borrow().get_scroll().1;
+
+    // Pulled past the top with resistance, then bouncing back once released
+    app.on_mouse_down(20., 10., MouseButton::Primary, Modifiers::default());
+    app.on_mouse_move(20., 50.);
+    assert_eq!(scroll(), -20.);
+    app.tick_with(16.);
+    app.on_mouse_up(20., 50., MouseButton::Primary, Modifiers::default());
+    for _ in 0..60 {
+        app.tick_with(16.);
+    }
+    assert_eq!(scroll(), 0.);
+
+    // A flick keeps scrolling after the touch ends, until the items run out
+    app.on_touch_start(1, 20., 80.);
+    app.tick_with(16.);
+    app.on_touch_move(1, 20., 50.);
+    assert_eq!(scroll(), 30.);
+    app.tick_with(16.);
+    app.on_touch_end(1, 20., 50.);
+    app.tick_with(16.);
+    assert!(scroll() > 30.);
+    assert!(list.borrow().get_scroll_velocity().1 > 0.);
+    for _ in 0..120 {
+        app.tick_with(16.);
+    }
+    assert_eq!(scroll(), 100.);
+    assert_eq!(list.borrow().get_scroll_velocity(), (0., 0.));
+}